@@ -0,0 +1,27 @@
+#!/usr/bin/env python3
+"""A bot making random legal plays, as a starting point for bots speaking the engine protocol.
+
+Run it with `canasta_rl eval "bot:python3 bots/random_bot.py" random random random`.
+See src/bot.rs for the protocol.
+"""
+import json
+import random
+import sys
+
+
+def main():
+    state = None
+    for line in sys.stdin:
+        command, _, rest = line.strip().partition(" ")
+        if command == "canasta":
+            print("ready RandomPythonBot", flush=True)
+        elif command == "state":
+            state = json.loads(rest)
+        elif command == "go":
+            print("play " + random.choice(state["legal_plays"]), flush=True)
+        elif command == "quit":
+            break
+
+
+if __name__ == "__main__":
+    main()
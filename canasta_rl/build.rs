@@ -0,0 +1,17 @@
+fn main() {
+    // Writes the header of the C API to include/canasta.h
+    #[cfg(feature = "capi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .expect("Could not read cbindgen.toml");
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{}/src/capi.rs", crate_dir))
+            .generate()
+            .expect("Could not generate the C header")
+            .write_to_file(format!("{}/include/canasta.h", crate_dir));
+    }
+}
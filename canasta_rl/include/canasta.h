@@ -0,0 +1,118 @@
+#ifndef CANASTA_H
+#define CANASTA_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Length of the observation written by `canasta_observation`.
+ */
+#define CANASTA_STATE_SIZE 190
+
+/**
+ * Number of actions, i.e. the length of the legal mask.
+ */
+#define CANASTA_ACTION_SIZE 39
+
+/**
+ * Number of seats, i.e. the number of scores.
+ */
+#define CANASTA_PLAYERS 4
+
+typedef enum CanastaStatus {
+  CANASTA_STATUS_OK = 0,
+  CANASTA_STATUS_NULL_POINTER = 1,
+  CANASTA_STATUS_BUFFER_TOO_SMALL = 2,
+  CANASTA_STATUS_INVALID_ACTION = 3,
+  CANASTA_STATUS_ILLEGAL_ACTION = 4,
+  CANASTA_STATUS_GAME_OVER = 5,
+} CanastaStatus;
+
+/**
+ * A game between four seats, partners sitting opposite each other.
+ */
+typedef struct CanastaGame CanastaGame;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a game dealt from `seed`. Release it with `canasta_game_free`.
+ */
+struct CanastaGame *canasta_game_new(uint64_t seed);
+
+/**
+ * Releases a game. Does nothing if `game` is null.
+ *
+ * # Safety
+ * `game` must come from `canasta_game_new` and not have been released yet.
+ */
+void canasta_game_free(struct CanastaGame *game);
+
+/**
+ * Deals a new game from `seed` in place of `game`.
+ *
+ * # Safety
+ * `game` must be null or a live game.
+ */
+enum CanastaStatus canasta_game_reset(struct CanastaGame *game, uint64_t seed);
+
+/**
+ * The seat to play, or -1 if `game` is null.
+ *
+ * # Safety
+ * `game` must be null or a live game.
+ */
+int32_t canasta_current_player(const struct CanastaGame *game);
+
+/**
+ * Whether the game is over, false if `game` is null.
+ *
+ * # Safety
+ * `game` must be null or a live game.
+ */
+bool canasta_finished(const struct CanastaGame *game);
+
+/**
+ * Writes the encoding of the game seen by the seat to play, `CANASTA_STATE_SIZE` floats.
+ *
+ * # Safety
+ * `game` must be null or a live game and `out` must be null or point to `len` floats.
+ */
+enum CanastaStatus canasta_observation(const struct CanastaGame *game, float *out, size_t len);
+
+/**
+ * Writes the legality of every action of the seat to play, `CANASTA_ACTION_SIZE` booleans.
+ *
+ * # Safety
+ * `game` must be null or a live game and `out` must be null or point to `len` booleans.
+ */
+enum CanastaStatus canasta_legal_mask(const struct CanastaGame *game, bool *out, size_t len);
+
+/**
+ * Makes `action` for the seat to play.
+ *
+ * # Safety
+ * `game` must be null or a live game.
+ */
+enum CanastaStatus canasta_apply_action(struct CanastaGame *game, size_t action);
+
+/**
+ * Writes the score of every seat, `CANASTA_PLAYERS` values.
+ *
+ * # Safety
+ * `game` must be null or a live game and `out` must be null or point to `len` values.
+ */
+enum CanastaStatus canasta_scores(const struct CanastaGame *game, int16_t *out, size_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CANASTA_H */
@@ -0,0 +1,8 @@
+"""Python bindings for the Canasta engine.
+
+Build with `maturin develop --release` from the crate directory.
+"""
+from ._canasta import ACTION_SIZE, PLAYERS, STATE_SIZE, Game, action_name
+from .env import CanastaEnv, env
+
+__all__ = ["ACTION_SIZE", "PLAYERS", "STATE_SIZE", "Game", "action_name", "CanastaEnv", "env"]
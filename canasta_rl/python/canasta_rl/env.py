@@ -0,0 +1,92 @@
+"""PettingZoo AEC environment for Canasta, backed by the Rust engine.
+
+Every seat is an agent, `player_0` to `player_3`, with partners sitting opposite each other. A
+turn is made of several actions (draw, melds, discard), so the same agent is usually selected
+several times in a row. Observations are dictionaries holding the encoding used for training and
+the mask of legal actions. Rewards are only given at the end of the game: the score of the
+agent's team minus the best score of the other teams.
+"""
+import functools
+
+import numpy as np
+from gymnasium import spaces
+from pettingzoo import AECEnv
+from pettingzoo.utils import wrappers
+
+from ._canasta import ACTION_SIZE, PLAYERS, STATE_SIZE, Game
+
+
+def env(render_mode=None):
+    """The environment wrapped in the usual PettingZoo checks."""
+    return wrappers.OrderEnforcingWrapper(CanastaEnv(render_mode=render_mode))
+
+
+class CanastaEnv(AECEnv):
+    metadata = {"name": "canasta_v0", "render_modes": ["human", "ansi"], "is_parallelizable": False}
+
+    def __init__(self, render_mode=None):
+        super().__init__()
+        self.render_mode = render_mode
+        self.possible_agents = [f"player_{i}" for i in range(PLAYERS)]
+        self.game = Game()
+
+    @functools.lru_cache(maxsize=None)
+    def observation_space(self, agent):
+        return spaces.Dict(
+            {
+                "observation": spaces.Box(-np.inf, np.inf, shape=(STATE_SIZE,), dtype=np.float32),
+                "action_mask": spaces.Box(0, 1, shape=(ACTION_SIZE,), dtype=np.int8),
+            }
+        )
+
+    @functools.lru_cache(maxsize=None)
+    def action_space(self, agent):
+        return spaces.Discrete(ACTION_SIZE)
+
+    def reset(self, seed=None, options=None):
+        self.game.reset(seed)
+        self.agents = self.possible_agents[:]
+        self.rewards = {agent: 0.0 for agent in self.agents}
+        self._cumulative_rewards = {agent: 0.0 for agent in self.agents}
+        self.terminations = {agent: False for agent in self.agents}
+        self.truncations = {agent: False for agent in self.agents}
+        self.infos = {agent: {} for agent in self.agents}
+        # The engine only encodes the game for the seat to play, the other agents see what they
+        # saw when they last played
+        self._observations = {agent: np.zeros(STATE_SIZE, dtype=np.float32) for agent in self.agents}
+        self.agent_selection = self.agents[self.game.current_player]
+
+    def observe(self, agent):
+        if agent == self.agent_selection and not self.game.finished:
+            self._observations[agent] = self.game.observation()
+            mask = self.game.legal_mask().astype(np.int8)
+        else:
+            mask = np.zeros(ACTION_SIZE, dtype=np.int8)
+        return {"observation": self._observations[agent], "action_mask": mask}
+
+    def step(self, action):
+        agent = self.agent_selection
+        if self.terminations[agent] or self.truncations[agent]:
+            self._was_dead_step(action)
+            return
+        self._cumulative_rewards[agent] = 0.0
+        self._observations[agent] = self.game.observation()
+        _, done = self.game.step(int(action))
+        if done:
+            self.rewards = dict(zip(self.agents, self.game.rewards()))
+            self.terminations = {a: True for a in self.agents}
+        else:
+            self.rewards = {a: 0.0 for a in self.agents}
+        self.agent_selection = self.agents[self.game.current_player]
+        self._accumulate_rewards()
+        if self.render_mode == "human":
+            self.render()
+
+    def render(self):
+        if self.render_mode == "ansi":
+            return str(self.game)
+        if self.render_mode == "human":
+            print(self.game)
+
+    def close(self):
+        pass
@@ -0,0 +1,48 @@
+"""Plays through the PettingZoo environment. Run with `pytest python/tests` after `maturin develop`."""
+import numpy as np
+
+from canasta_rl.env import CanastaEnv, env
+
+
+def play_game(seed):
+    """Plays the first legal action of every agent, returning the final rewards and the actions."""
+    game = env()
+    game.reset(seed=seed)
+    actions = []
+    rewards = {}
+    for agent in game.agent_iter():
+        observation, reward, termination, truncation, _ = game.last()
+        if termination or truncation:
+            rewards[agent] = reward
+            game.step(None)
+            continue
+        mask = observation["action_mask"]
+        assert observation["observation"].dtype == np.float32
+        assert mask.any()
+        action = int(np.flatnonzero(mask)[0])
+        actions.append(action)
+        game.step(action)
+    game.close()
+    return rewards, actions
+
+
+def test_seeded_game_plays_to_the_end():
+    rewards, actions = play_game(7)
+    assert actions
+    assert len(rewards) == 4
+    # Partners share a reward, and the two teams' rewards cancel out
+    assert rewards["player_0"] == rewards["player_2"]
+    assert rewards["player_1"] == rewards["player_3"]
+    assert rewards["player_0"] == -rewards["player_1"]
+
+
+def test_same_seed_same_game():
+    assert play_game(7) == play_game(7)
+
+
+def test_other_agents_cannot_act():
+    game = CanastaEnv()
+    game.reset(seed=7)
+    for agent in game.agents:
+        if agent != game.agent_selection:
+            assert not game.observe(agent)["action_mask"].any()
@@ -0,0 +1,229 @@
+//! Line-based protocol for bots running in another process.
+//!
+//! The engine starts the bot and writes one command per line to its stdin, reading the replies
+//! from its stdout:
+//!
+//! - `canasta 1`: sent once on start with the protocol version, the bot answers `ready [name]`
+//! - `state <json>`: what the seat to play sees, as the `Observation` of the game server
+//! - `legal <plays>`: the legal plays of the seat in notation, separated by spaces
+//! - `go`: the bot answers `play <play>` with one of the legal plays, e.g. `play M:K`
+//! - `illegal <play>`: the last reply was not a legal play, it is followed by another `go`
+//! - `quit`: the bot should exit
+//!
+//! Any other line from the bot, e.g. `info ...`, is ignored. A bot may be asked for the plays of
+//! several seats and games, so everything it needs is in `state`.
+
+use crate::canastautil::{self, Game, GameState, Play, PLAYERS_PER_TEAM, TEAMS_COUNT};
+use crate::model_eval::CanastaAgent;
+use crate::server::observe;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const PROTOCOL_VERSION: u8 = 1;
+// Illegal replies in a row before the bot is given up on
+const MAX_ATTEMPTS: usize = 3;
+// How long a bot has to exit once told to quit
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+struct BotProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl BotProcess {
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("Could not write to the bot: {}", e))
+    }
+
+    // Reads lines until one starting with `command`, returning the rest of it
+    fn expect(&mut self, command: &str) -> Result<String, String> {
+        loop {
+            let mut line = String::new();
+            let read = self
+                .stdout
+                .read_line(&mut line)
+                .map_err(|e| format!("Could not read from the bot: {}", e))?;
+            if read == 0 {
+                return Err(format!(
+                    "The bot exited while the engine waited for `{}`",
+                    command
+                ));
+            }
+            let mut words = line.trim().splitn(2, ' ');
+            if words.next() == Some(command) {
+                return Ok(words.next().unwrap_or("").trim().to_string());
+            }
+        }
+    }
+}
+
+/// A bot in another process, speaking the protocol above.
+pub struct ExternalAgent {
+    name: String,
+    process: Mutex<BotProcess>,
+}
+
+impl ExternalAgent {
+    /// Starts `command`, a program followed by its arguments separated by spaces.
+    pub fn spawn(command: &str) -> Result<ExternalAgent, String> {
+        let mut words = command.split_whitespace();
+        let mut process = Command::new(words.next().ok_or("Empty bot command")?);
+        process.args(words);
+        ExternalAgent::start(process, command)
+    }
+
+    // Starts the bot and waits for it to be ready, `command` names it if it does not
+    fn start(mut process: Command, command: &str) -> Result<ExternalAgent, String> {
+        let mut child = process
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not start {}: {}", command, e))?;
+        let mut process = BotProcess {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+        };
+        process.send(&format!("canasta {}", PROTOCOL_VERSION))?;
+        let name = process.expect("ready")?;
+        Ok(ExternalAgent {
+            name: if name.is_empty() {
+                command.to_string()
+            } else {
+                name
+            },
+            process: Mutex::new(process),
+        })
+    }
+
+    /// Asks the bot for the play of the seat to play in `game`.
+    pub fn ask(&self, game: &Game) -> Result<Play, String> {
+        let observation = observe(0, game, Some(game.turn.get()));
+        let legal: Vec<Play> = observation
+            .legal_plays
+            .iter()
+            .map(|play| play.parse().unwrap())
+            .collect();
+        let mut process = self.process.lock().unwrap();
+        process.send(&format!(
+            "state {}",
+            serde_json::to_string(&observation).unwrap()
+        ))?;
+        process.send(&format!("legal {}", observation.legal_plays.join(" ")))?;
+        for _ in 0..MAX_ATTEMPTS {
+            process.send("go")?;
+            let reply = process.expect("play")?;
+            match reply.parse::<Play>() {
+                Ok(play) if legal.contains(&play) => return Ok(play),
+                _ => process.send(&format!("illegal {}", reply))?,
+            }
+        }
+        Err(format!(
+            "{} made {} illegal plays in a row",
+            self.name, MAX_ATTEMPTS
+        ))
+    }
+}
+
+impl CanastaAgent for ExternalAgent {
+    // A bot that fails to answer is not allowed to end the game, a random legal play stands in
+    fn get_action(&self, state: &GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>) -> Play {
+        match self.ask(&state.game) {
+            Ok(play) => play,
+            Err(e) => {
+                println!("{}, playing a random legal play instead", e);
+                canastautil::random_legal_action(&state.game).play
+            }
+        }
+    }
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Drop for ExternalAgent {
+    // Asks the bot to quit, and kills it if it has not within `QUIT_TIMEOUT`
+    fn drop(&mut self) {
+        let process = match self.process.get_mut() {
+            Ok(process) => process,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        // The bot may already be gone
+        let _ = process.send("quit");
+        let start = Instant::now();
+        while start.elapsed() < QUIT_TIMEOUT {
+            match process.child.try_wait() {
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+                _ => return,
+            }
+        }
+        let _ = process.child.kill();
+        let _ = process.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plays the first legal play it is given
+    const FIRST_LEGAL_BOT: &str = "while read command rest; do case $command in \
+        canasta) echo 'ready FirstLegal';; \
+        legal) first=${rest%% *};; \
+        go) echo 'info thinking'; echo \"play $first\";; \
+        quit) exit;; \
+        esac; done";
+
+    fn spawn_sh(script: &str) -> ExternalAgent {
+        let mut process = Command::new("sh");
+        process.args(["-c", script]);
+        ExternalAgent::start(process, "sh").unwrap()
+    }
+
+    #[test]
+    fn external_bot_plays_a_game() {
+        let bot = spawn_sh(FIRST_LEGAL_BOT);
+        assert_eq!(bot.name(), "FirstLegal");
+        let mut game = Game::new_seeded(2, 2, 2, 13, 5);
+        while !game.finished {
+            let play = bot.ask(&game).unwrap();
+            assert!(game.check_legal(play));
+            game.execute_play(play);
+        }
+    }
+
+    #[test]
+    fn illegal_replies_are_rejected() {
+        let bot = spawn_sh(
+            "while read command rest; do case $command in \
+             canasta) echo ready;; go) echo 'play X:JK';; quit) exit;; esac; done",
+        );
+        let game = Game::new_seeded(2, 2, 2, 13, 5);
+        // Nobody may discard before drawing
+        assert!(bot.ask(&game).is_err());
+    }
+
+    #[test]
+    fn bots_ignoring_quit_are_killed() {
+        let bot = spawn_sh("read command; echo ready; trap '' TERM; while true; do sleep 1; done");
+        let start = Instant::now();
+        drop(bot);
+        assert!(start.elapsed() < QUIT_TIMEOUT * 3);
+    }
+
+    #[test]
+    fn failing_bot_falls_back_to_a_legal_play() {
+        let bot = spawn_sh("read command; echo ready");
+        let state = GameState::<PLAYERS_PER_TEAM, TEAMS_COUNT> {
+            game: Game::new_seeded(2, 2, 2, 13, 5),
+        };
+        let play = bot.get_action(&state);
+        assert!(state.game.check_legal(play));
+    }
+}
@@ -1,8 +1,10 @@
 #![allow(dead_code)]
 
-use canasta_rl::mdp::{Agent, State};
+use crate::mdp::State;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
+use rand::{Rng, SeedableRng};
 use std::fmt;
 use std::fs::OpenOptions;
 use std::hash::Hash;
@@ -12,6 +14,11 @@ use std::slice::Iter;
 use std::sync::{Arc, Mutex};
 extern crate rand;
 
+pub mod belief;
+pub mod env;
+pub mod notation;
+pub mod table;
+
 const DEBUG: bool = false;
 
 pub const ACTION_SIZE: usize = 39;
@@ -22,6 +29,8 @@ pub const PLAYERS_PER_TEAM: u8 = 2;
 pub const TEAMS_COUNT: u8 = 2;
 pub const DECKS: u8 = 2;
 pub const HAND_SIZE: u8 = 13;
+/// Number of kinds of cards, from the joker to the ace, and so of entries in a count of cards.
+pub const CARD_KINDS: usize = 14;
 
 //Game: Canasta
 //Util Functions
@@ -39,51 +48,82 @@ pub enum Play {
     GoOut,
 }
 
+// Declares the canonical action table: the position of a play in this list is its action
+// index, and its name is the variant followed by the card, if any. Every conversion between
+// plays, indices, one-hot vectors and names goes through the generated tables.
+macro_rules! action_table {
+    ($($variant:ident $(($card:ident))?),* $(,)?) => {
+        static PLAYS: [Play; ACTION_SIZE] = [$(action_table!(@play $variant $($card)?)),*];
+        static PLAY_NAMES: [&str; ACTION_SIZE] =
+            [$(concat!(stringify!($variant) $(, " ", stringify!($card))?)),*];
+    };
+    (@play Discard $card:ident) => { Play::Discard(Card::$card) };
+    (@play $variant:ident $card:ident) => { Play::$variant(PlayableCardSubset::$card) };
+    (@play $variant:ident) => { Play::$variant };
+}
+
+action_table! {
+    Discard(Joker),
+    Discard(Two),
+    Discard(Three),
+    Discard(Four),
+    Discard(Five),
+    Discard(Six),
+    Discard(Seven),
+    Discard(Eight),
+    Discard(Nine),
+    Discard(Ten),
+    Discard(Jack),
+    Discard(Queen),
+    Discard(King),
+    Discard(Ace),
+    Draw,
+    PickupPile,
+    GoOut,
+    PlaceWild(Four),
+    PlaceWild(Five),
+    PlaceWild(Six),
+    PlaceWild(Seven),
+    PlaceWild(Eight),
+    PlaceWild(Nine),
+    PlaceWild(Ten),
+    PlaceWild(Jack),
+    PlaceWild(Queen),
+    PlaceWild(King),
+    PlaceWild(Ace),
+    Play(Four),
+    Play(Five),
+    Play(Six),
+    Play(Seven),
+    Play(Eight),
+    Play(Nine),
+    Play(Ten),
+    Play(Jack),
+    Play(Queen),
+    Play(King),
+    Play(Ace),
+}
+
 impl Play {
+    /// Iterates over every play in action index order.
     pub fn iterator() -> Iter<'static, Play> {
-        static PLAYS: [Play; 39] = [
-            Play::Discard(Card::Joker),
-            Play::Discard(Card::Two),
-            Play::Discard(Card::Three),
-            Play::Discard(Card::Four),
-            Play::Discard(Card::Five),
-            Play::Discard(Card::Six),
-            Play::Discard(Card::Seven),
-            Play::Discard(Card::Eight),
-            Play::Discard(Card::Nine),
-            Play::Discard(Card::Ten),
-            Play::Discard(Card::Jack),
-            Play::Discard(Card::Queen),
-            Play::Discard(Card::King),
-            Play::Discard(Card::Ace),
-            Play::GoOut,
-            Play::PickupPile,
-            Play::Play(PlayableCardSubset::Four),
-            Play::Play(PlayableCardSubset::Five),
-            Play::Play(PlayableCardSubset::Six),
-            Play::Play(PlayableCardSubset::Seven),
-            Play::Play(PlayableCardSubset::Eight),
-            Play::Play(PlayableCardSubset::Nine),
-            Play::Play(PlayableCardSubset::Ten),
-            Play::Play(PlayableCardSubset::Jack),
-            Play::Play(PlayableCardSubset::Queen),
-            Play::Play(PlayableCardSubset::King),
-            Play::Play(PlayableCardSubset::Ace),
-            Play::PlaceWild(PlayableCardSubset::Four),
-            Play::PlaceWild(PlayableCardSubset::Five),
-            Play::PlaceWild(PlayableCardSubset::Six),
-            Play::PlaceWild(PlayableCardSubset::Seven),
-            Play::PlaceWild(PlayableCardSubset::Eight),
-            Play::PlaceWild(PlayableCardSubset::Nine),
-            Play::PlaceWild(PlayableCardSubset::Ten),
-            Play::PlaceWild(PlayableCardSubset::Jack),
-            Play::PlaceWild(PlayableCardSubset::Queen),
-            Play::PlaceWild(PlayableCardSubset::King),
-            Play::PlaceWild(PlayableCardSubset::Ace),
-            Play::Draw,
-        ];
         PLAYS.iter()
     }
+    /// The action index of this play.
+    pub fn index(&self) -> usize {
+        PLAYS.iter().position(|play| play == self).unwrap()
+    }
+    /// The play with the given action index.
+    pub fn from_index(i: usize) -> Play {
+        match PLAYS.get(i) {
+            Some(play) => *play,
+            None => panic!("Invalid action index: {}", i),
+        }
+    }
+    /// A human readable name, e.g. `Discard Joker` or `PlaceWild Seven`.
+    pub fn name(&self) -> &'static str {
+        PLAY_NAMES[self.index()]
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Hash, Eq, Debug)]
@@ -325,6 +365,9 @@ impl From<WildCardSubset> for Card {
     }
 }
 
+// Number of copies of each card (by index) in one deck, as `DrawPile::new` deals them
+const DECK_COMPOSITION: [u8; CARD_KINDS] = [2, 4, 2, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4];
+
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 struct DrawPile {
     cards: Vec<Card>,
@@ -364,9 +407,8 @@ impl DrawPile {
         Self { cards }
     }
 
-    fn shuffle(&mut self) {
-        let mut rng = thread_rng();
-        self.cards.as_mut_slice().shuffle(&mut rng);
+    fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.cards.as_mut_slice().shuffle(rng);
     }
 
     fn draw(&mut self) -> Option<Card> {
@@ -376,6 +418,11 @@ impl DrawPile {
     fn is_empty(&self) -> bool {
         self.cards.is_empty()
     }
+
+    // Number of copies of each card (by index) in a full, undealt set of `decks` decks
+    fn composition(decks: u8) -> [u8; CARD_KINDS] {
+        DECK_COMPOSITION.map(|count| count * decks)
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
@@ -572,7 +619,7 @@ impl fmt::Display for Hand {
         for i in 0..14 {
             for _ in 0..self.hand[i] {
                 let card = Card::from_index(i);
-                cards.push((*card.get_simple_string()).to_string());
+                cards.push(card.notation().to_string());
             }
         }
         write!(f, "{:?}", cards)
@@ -624,6 +671,8 @@ struct Player {
     hand: Hand,
     board: Arc<Mutex<Board>>,
     knowledge: Vec<[i8; 14]>,
+    // Cards every other seat knows to still be in this hand (picked up from the discard pile)
+    revealed: [u8; 14],
 }
 impl PartialEq for Player {
     fn eq(&self, other: &Self) -> bool {
@@ -651,6 +700,15 @@ impl Player {
             hand: Hand::new(),
             board: board,
             knowledge: knowledge,
+            revealed: [0; 14],
+        }
+    }
+    // Drops revealed cards which may have left the hand, given a knowledge update
+    fn forget_revealed(&mut self, knowledge_update: &[i8; 14]) {
+        for (revealed, update) in self.revealed.iter_mut().zip(knowledge_update) {
+            if *update < 0 {
+                *revealed = revealed.saturating_sub(update.unsigned_abs());
+            }
         }
     }
 }
@@ -679,29 +737,133 @@ impl TurnCounter {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+// Equality and hashing leave out the seed, so that the same position reached from different
+// deals is the same state
+#[derive(Clone, Debug)]
 pub struct Game {
     draw_pile: DrawPile,
     discard_pile: Vec<Card>,
     players: Vec<Player>,
     players_per_team: u8,
     teams_count: u8,
+    decks: u8,
+    hand_size: u8,
+    seed: u64,
     pub finished: bool,
     frozen: bool,
     pub turn: TurnCounter,
     curr_player_drawn: bool,
 }
 
+impl PartialEq for Game {
+    fn eq(&self, other: &Self) -> bool {
+        self.draw_pile == other.draw_pile
+            && self.discard_pile == other.discard_pile
+            && self.players == other.players
+            && self.players_per_team == other.players_per_team
+            && self.teams_count == other.teams_count
+            && self.decks == other.decks
+            && self.hand_size == other.hand_size
+            && self.finished == other.finished
+            && self.frozen == other.frozen
+            && self.turn == other.turn
+            && self.curr_player_drawn == other.curr_player_drawn
+    }
+}
+impl Eq for Game {}
+impl Hash for Game {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.draw_pile.hash(state);
+        self.discard_pile.hash(state);
+        self.players.hash(state);
+        self.players_per_team.hash(state);
+        self.teams_count.hash(state);
+        self.decks.hash(state);
+        self.hand_size.hash(state);
+        self.finished.hash(state);
+        self.frozen.hash(state);
+        self.turn.hash(state);
+        self.curr_player_drawn.hash(state);
+    }
+}
+
 impl std::fmt::Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        self.fmt_table(f, None)
+    }
+}
+
+/// A game as seen from one seat: the other hands only show their size and the cards known to be
+/// in them.
+pub struct SeatView<'a> {
+    game: &'a Game,
+    seat: u8,
+}
+
+impl fmt::Display for SeatView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.game.fmt_table(f, Some(self.seat))
+    }
+}
+
+impl Game {
+    pub fn seat_view(&self, seat: u8) -> SeatView<'_> {
+        SeatView { game: self, seat }
+    }
+    // Writes the table, hiding every hand but `seat`'s if given
+    fn fmt_table(&self, f: &mut fmt::Formatter, seat: Option<u8>) -> fmt::Result {
+        let players_count = self.teams_count * self.players_per_team;
+        let discard_pile: Vec<&str> = self.discard_pile.iter().map(|c| c.notation()).collect();
+        writeln!(
+            f,
+            "Turn: {}, Seat {} to play{}{}",
+            self.turn.total_turns,
+            self.turn.get(),
+            if self.curr_player_drawn { " (has drawn)" } else { "" },
+            if self.finished { ", finished" } else { "" }
+        )?;
+        writeln!(
+            f,
+            "Stock: {}, Discard Pile: {:?}{}",
+            self.draw_pile.cards.len(),
+            discard_pile,
+            if self.frozen { " (frozen)" } else { "" }
+        )?;
+        for i in 0..players_count {
+            let marker = if i == self.turn.get() { ">" } else { " " };
+            let player = &self.players[i as usize];
+            write!(f, "{} Seat {} (Team {}) ", marker, i, i % self.teams_count)?;
+            if seat.is_none() || seat == Some(i) {
+                writeln!(f, "Hand: {}", player.hand)?;
+            } else {
+                let known = Hand {
+                    hand: player.revealed,
+                };
+                writeln!(f, "Cards: {}, Known: {}", player.hand.get_hand_size(), known)?;
+            }
+        }
+        for team in 0..self.teams_count {
+            let board = self.players[team as usize].board.lock().unwrap();
+            writeln!(f, "Team {} Board ({}): {}", team, board.get_score(), board)?;
+        }
+        Ok(())
     }
 }
 
 impl Game {
     pub fn new(teams_count: u8, players_per_team: u8, decks: u8, hand_size: u8) -> Game {
+        Game::new_seeded(teams_count, players_per_team, decks, hand_size, thread_rng().gen())
+    }
+    /// Creates a game whose deal is fully determined by `seed`, so it can be replayed.
+    pub fn new_seeded(
+        teams_count: u8,
+        players_per_team: u8,
+        decks: u8,
+        hand_size: u8,
+        seed: u64,
+    ) -> Game {
         let mut draw_pile = DrawPile::new(decks);
-        draw_pile.shuffle();
+        draw_pile.shuffle(&mut StdRng::seed_from_u64(seed));
         let mut players: Vec<Player> = Vec::new();
         let mut boards: Vec<Arc<Mutex<Board>>> = Vec::new();
         for _ in 0..teams_count {
@@ -726,6 +888,9 @@ impl Game {
             players: players,
             teams_count: teams_count,
             players_per_team: players_per_team,
+            decks,
+            hand_size,
+            seed,
             finished: false,
             frozen: (up_card == Card::Joker || up_card == Card::Two),
             turn: TurnCounter::new(players_per_team * teams_count),
@@ -735,6 +900,9 @@ impl Game {
     pub fn get_total_turns(&self) -> u16 {
         self.turn.total_turns
     }
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
     fn draw(&mut self) -> Card {
         let card: Option<Card> = self.draw_pile.draw();
         if card.is_none() {
@@ -768,11 +936,45 @@ impl Game {
         }
         scores
     }
+    /// Rewards by seat: once the game is over, the score of the seat's team minus the best score
+    /// of the other teams, zero before that.
+    pub fn rewards(&self) -> Vec<f64> {
+        let players_count = (self.teams_count * self.players_per_team) as usize;
+        if !self.finished {
+            return vec![0.0; players_count];
+        }
+        let scores = self.get_scores();
+        let teams = self.teams_count as usize;
+        (0..players_count)
+            .map(|seat| {
+                let best_other = (0..players_count)
+                    .filter(|other| other % teams != seat % teams)
+                    .map(|other| scores[other])
+                    .max()
+                    .unwrap_or(0);
+                (scores[seat] - best_other) as f64
+            })
+            .collect()
+    }
     pub fn check_legal(&self, play: Play) -> bool {
+        let board: &Board = &self.get_curr_player().board.lock().unwrap();
+        self.check_legal_on(play, board)
+    }
+    /// Legality of every play, by action index. The board is only locked once, so prefer this
+    /// over calling `check_legal` for each play.
+    pub fn legal_mask(&self) -> [bool; ACTION_SIZE] {
+        let board: &Board = &self.get_curr_player().board.lock().unwrap();
+        let mut mask: [bool; ACTION_SIZE] = [false; ACTION_SIZE];
+        for (i, legal) in mask.iter_mut().enumerate() {
+            *legal = self.check_legal_on(Action::from(i).play, board);
+        }
+        mask
+    }
+    // `board` must be the (already locked) board of the current player
+    fn check_legal_on(&self, play: Play, board: &Board) -> bool {
         let player: &Player = self.get_curr_player();
         let hand: &Hand = &player.hand;
         let hand_size: u8 = hand.get_hand_size();
-        let board: &Board = &player.board.lock().unwrap();
         match play {
             Play::GoOut => {
                 self.curr_player_drawn
@@ -1011,6 +1213,7 @@ impl Game {
                         .unwrap()
                         .place_card(top_card, 1);
                 }
+                self.get_curr_player_mut().forget_revealed(&knowledge_update);
                 let mut new_cards: Vec<Card> = Vec::new();
                 for card in self.discard_pile.iter() {
                     new_cards.push(*card);
@@ -1018,8 +1221,10 @@ impl Game {
                 }
                 for card in new_cards.iter() {
                     self.get_curr_player_mut().hand.add(*card, 1);
+                    self.get_curr_player_mut().revealed[card.get_index()] += 1;
                 }
                 self.get_curr_player_mut().hand.remove(top_card, 1);
+                self.get_curr_player_mut().revealed[top_card.get_index()] -= 1;
                 knowledge_update[top_card.get_index()] -= 1;
                 self.frozen = false;
                 self.discard_pile.clear();
@@ -1133,6 +1338,10 @@ impl Game {
                 }
             }
         }
+        // A drawn card is never seen by the others, and a pile pickup was handled above
+        if play != Play::Draw && play != Play::PickupPile {
+            self.players[current_player_index as usize].forget_revealed(&knowledge_update);
+        }
         for i in current_player_index + 1
             ..current_player_index + (self.teams_count * self.players_per_team)
         {
@@ -1158,6 +1367,62 @@ impl Game {
             self.get_curr_player_mut().board.lock().unwrap().went_out = true;
         }
     }
+    /// Clones the game together with its boards. A plain `clone` shares the boards with the
+    /// original, so plays on it would also show up on the original game.
+    pub fn detached_clone(&self) -> Game {
+        let mut game = self.clone();
+        let players_count = self.teams_count * self.players_per_team;
+        for team in 0..self.teams_count {
+            let board = self.players[team as usize].board.lock().unwrap().clone();
+            let board = Arc::new(Mutex::new(board));
+            for i in (team..players_count).step_by(self.teams_count as usize) {
+                game.players[i as usize].board = board.clone();
+            }
+        }
+        game
+    }
+    /// Samples a full game that is consistent with everything `seat` has observed. The unseen
+    /// cards are dealt at random to the unknown part of the other hands and to the stock, while
+    /// hand sizes, cards known from pile pickups and everything public are kept. The returned
+    /// game has its own boards and can be played out freely.
+    ///
+    /// The cards known to be in a hand are those of `Player::revealed` rather than `knowledge`:
+    /// `knowledge` is a running balance of the cards a seat was seen to take and to play, which
+    /// goes negative once a seat plays cards nobody saw it take, so it is no count of what the
+    /// hand holds. `revealed` keeps only the picked up cards still in the hand.
+    pub fn determinize<R: Rng + ?Sized>(&self, seat: u8, rng: &mut R) -> Game {
+        let mut belief = self.belief(seat);
+        let mut game = self.detached_clone();
+        // The threes of a seat going out leave the game without being seen, and nothing else does
+        let unknown: u16 = belief.hands.iter().map(|hand| hand.unknown as u16).sum();
+        let hidden = unknown + belief.stock as u16;
+        debug_assert!(belief.unseen_total() >= hidden, "More hidden cards than unseen ones");
+        let removed_threes = belief.unseen_total().saturating_sub(hidden);
+        debug_assert!(
+            removed_threes == 0 || self.finished,
+            "Unseen cards do not match the hands and the stock"
+        );
+        debug_assert!(removed_threes <= belief.unseen[Card::Three.get_index()] as u16);
+        let threes = &mut belief.unseen[Card::Three.get_index()];
+        *threes = threes.saturating_sub(removed_threes as u8);
+        let mut unseen: Vec<Card> = Vec::new();
+        for card in Card::iterator() {
+            for _ in 0..belief.unseen[card.get_index()] {
+                unseen.push(*card);
+            }
+        }
+        unseen.shuffle(rng);
+        for hand in belief.hands.iter() {
+            let player = &mut game.players[hand.player as usize];
+            player.hand = Hand { hand: hand.known };
+            for _ in 0..hand.unknown {
+                player.hand.add(unseen.pop().unwrap(), 1);
+            }
+        }
+        debug_assert_eq!(unseen.len(), belief.stock as usize);
+        game.draw_pile.cards = unseen;
+        game
+    }
 }
 
 #[derive(PartialEq, Eq, Hash, Clone)]
@@ -1185,19 +1450,25 @@ impl State for GameState<1, 2> {
     }
     fn actions(&self) -> Vec<Action> {
         let mut actions: Vec<Action> = Vec::new();
-        for play in Play::iterator() {
-            if self.game.check_legal(*play) {
-                actions.push(Action { play: *play });
+        for (i, legal) in self.game.legal_mask().iter().enumerate() {
+            if *legal {
+                actions.push(Action::from(i));
             }
         }
         actions
     }
+    fn random_action(&self) -> Action {
+        random_legal_action(&self.game)
+    }
     fn check_legal(&self, _play: usize) -> bool {
         self.game.check_legal(Action::from(_play).play)
     }
     fn check_legal_action(&self, _play: Action) -> bool {
         self.game.check_legal(_play.play)
     }
+    fn legal_mask(&self) -> Vec<bool> {
+        self.game.legal_mask().to_vec()
+    }
 }
 
 impl State for GameState<2, 2> {
@@ -1216,17 +1487,40 @@ impl State for GameState<2, 2> {
     }
     fn actions(&self) -> Vec<Action> {
         let mut actions: Vec<Action> = Vec::new();
-        for play in Play::iterator() {
-            actions.push(Action { play: *play });
+        for (i, legal) in self.game.legal_mask().iter().enumerate() {
+            if *legal {
+                actions.push(Action::from(i));
+            }
         }
         actions
     }
+    fn random_action(&self) -> Action {
+        random_legal_action(&self.game)
+    }
     fn check_legal(&self, _play: usize) -> bool {
         self.game.check_legal(Action::from(_play).play)
     }
     fn check_legal_action(&self, _play: Action) -> bool {
         self.game.check_legal(_play.play)
     }
+    fn legal_mask(&self) -> Vec<bool> {
+        self.game.legal_mask().to_vec()
+    }
+}
+
+// Picks uniformly among the legal actions of the current player
+pub fn random_legal_action(game: &Game) -> Action {
+    let legal: Vec<usize> = game
+        .legal_mask()
+        .iter()
+        .enumerate()
+        .filter(|(_, legal)| **legal)
+        .map(|(i, _)| i)
+        .collect();
+    match legal.choose(&mut thread_rng()) {
+        Some(i) => Action::from(*i),
+        None => panic!("No legal plays"),
+    }
 }
 
 impl From<GameState<1, 2>> for [f32; 160] {
@@ -1359,316 +1653,30 @@ impl From<GameState<2, 2>> for [f32; 190] {
     }
 }
 
-pub struct CanastaAgent<const PLAYERS_PER_TEAM: u8, const TEAMS_COUNT: u8> {
-    pub state: Arc<Mutex<GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>>>,
-
-    pub player_id: u8,
-}
-impl Agent<GameState<1, 2>> for CanastaAgent<1, 2> {
-    fn current_state(&self) -> GameState<1, 2> {
-        let mut i = 0;
-        loop {
-            let state = self.state.lock().unwrap();
-            if state.game.finished {
-                return state.clone();
-            }
-            if state.game.turn.get() == self.player_id {
-                //println!("{}", i);
-                return state.clone();
-            } else {
-                i += 1;
-                drop(state);
-                std::thread::sleep(std::time::Duration::from_nanos(1));
-            }
-        }
-    }
-    fn take_action(&mut self, action: &Action) -> () {
-        let mut state = self.state.lock().unwrap();
-        if state.game.finished {
-            return;
-        }
-        if DEBUG {
-            println!("Turn: {}", state.game.turn.get());
-            if state.game.discard_pile.len() > 0 {
-                println!(
-                    "Top Discard Pile: {}",
-                    state.game.discard_pile[state.game.discard_pile.len() - 1]
-                );
-            } else {
-                println!("Top Discard Pile: None");
-            }
-            for player in state.game.players.iter() {
-                println!("Hand: {}", player.hand);
-                println!("Board: {}", player.board.lock().unwrap());
-            }
-            println!("Action: {:?}", (*action).play);
-            println!("");
-        }
-        state.game.execute_play((*action).play);
-    }
-}
-
-impl Agent<GameState<2, 2>> for CanastaAgent<2, 2> {
-    fn current_state(&self) -> GameState<2, 2> {
-        let mut i = 0;
-        loop {
-            let state = self.state.lock().unwrap();
-            if state.game.finished {
-                return state.clone();
-            }
-            if state.game.turn.get() == self.player_id {
-                //println!("{}", i);
-                return state.clone();
-            } else {
-                i += 1;
-                drop(state);
-                std::thread::sleep(std::time::Duration::from_nanos(1));
-            }
-        }
-    }
-    fn take_action(&mut self, action: &Action) -> () {
-        let mut state = self.state.lock().unwrap();
-        if state.game.finished {
-            return;
-        }
-        if DEBUG {
-            println!("Turn: {}", state.game.turn.get());
-            if state.game.discard_pile.len() > 0 {
-                println!(
-                    "Top Discard Pile: {}",
-                    state.game.discard_pile[state.game.discard_pile.len() - 1]
-                );
-            } else {
-                println!("Top Discard Pile: None");
-            }
-            for player in state.game.players.iter() {
-                println!("Hand: {}", player.hand);
-                println!("Board: {}", player.board.lock().unwrap());
-            }
-            println!("Action: {:?}", (*action).play);
-            println!("");
-        }
-        state.game.execute_play((*action).play);
-    }
-}
-
 impl From<Action> for usize {
     fn from(val: Action) -> Self {
-        match val.play {
-            Play::Discard(Card::Joker) => 0,
-            Play::Discard(Card::Two) => 1,
-            Play::Discard(Card::Three) => 2,
-            Play::Discard(Card::Four) => 3,
-            Play::Discard(Card::Five) => 4,
-            Play::Discard(Card::Six) => 5,
-            Play::Discard(Card::Seven) => 6,
-            Play::Discard(Card::Eight) => 7,
-            Play::Discard(Card::Nine) => 8,
-            Play::Discard(Card::Ten) => 9,
-            Play::Discard(Card::Jack) => 10,
-            Play::Discard(Card::Queen) => 11,
-            Play::Discard(Card::King) => 12,
-            Play::Discard(Card::Ace) => 13,
-            Play::Draw => 14,
-            Play::PickupPile => 15,
-            Play::GoOut => 16,
-            Play::PlaceWild(PlayableCardSubset::Four) => 17,
-            Play::PlaceWild(PlayableCardSubset::Five) => 18,
-            Play::PlaceWild(PlayableCardSubset::Six) => 19,
-            Play::PlaceWild(PlayableCardSubset::Seven) => 20,
-            Play::PlaceWild(PlayableCardSubset::Eight) => 21,
-            Play::PlaceWild(PlayableCardSubset::Nine) => 22,
-            Play::PlaceWild(PlayableCardSubset::Ten) => 23,
-            Play::PlaceWild(PlayableCardSubset::Jack) => 24,
-            Play::PlaceWild(PlayableCardSubset::Queen) => 25,
-            Play::PlaceWild(PlayableCardSubset::King) => 26,
-            Play::PlaceWild(PlayableCardSubset::Ace) => 27,
-            Play::Play(PlayableCardSubset::Four) => 28,
-            Play::Play(PlayableCardSubset::Five) => 29,
-            Play::Play(PlayableCardSubset::Six) => 30,
-            Play::Play(PlayableCardSubset::Seven) => 31,
-            Play::Play(PlayableCardSubset::Eight) => 32,
-            Play::Play(PlayableCardSubset::Nine) => 33,
-            Play::Play(PlayableCardSubset::Ten) => 34,
-            Play::Play(PlayableCardSubset::Jack) => 35,
-            Play::Play(PlayableCardSubset::Queen) => 36,
-            Play::Play(PlayableCardSubset::King) => 37,
-            Play::Play(PlayableCardSubset::Ace) => 38,
-        }
+        val.play.index()
     }
 }
 
 impl From<usize> for Action {
     fn from(val: usize) -> Self {
-        match val {
-            0 => Action {
-                play: Play::Discard(Card::Joker),
-            },
-            1 => Action {
-                play: Play::Discard(Card::Two),
-            },
-            2 => Action {
-                play: Play::Discard(Card::Three),
-            },
-            3 => Action {
-                play: Play::Discard(Card::Four),
-            },
-            4 => Action {
-                play: Play::Discard(Card::Five),
-            },
-            5 => Action {
-                play: Play::Discard(Card::Six),
-            },
-            6 => Action {
-                play: Play::Discard(Card::Seven),
-            },
-            7 => Action {
-                play: Play::Discard(Card::Eight),
-            },
-            8 => Action {
-                play: Play::Discard(Card::Nine),
-            },
-            9 => Action {
-                play: Play::Discard(Card::Ten),
-            },
-            10 => Action {
-                play: Play::Discard(Card::Jack),
-            },
-            11 => Action {
-                play: Play::Discard(Card::Queen),
-            },
-            12 => Action {
-                play: Play::Discard(Card::King),
-            },
-            13 => Action {
-                play: Play::Discard(Card::Ace),
-            },
-            14 => Action { play: Play::Draw },
-            15 => Action {
-                play: Play::PickupPile,
-            },
-            16 => Action { play: Play::GoOut },
-            17 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Four),
-            },
-            18 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Five),
-            },
-            19 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Six),
-            },
-            20 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Seven),
-            },
-            21 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Eight),
-            },
-            22 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Nine),
-            },
-            23 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Ten),
-            },
-            24 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Jack),
-            },
-            25 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Queen),
-            },
-            26 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::King),
-            },
-            27 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Ace),
-            },
-            28 => Action {
-                play: Play::Play(PlayableCardSubset::Four),
-            },
-            29 => Action {
-                play: Play::Play(PlayableCardSubset::Five),
-            },
-            30 => Action {
-                play: Play::Play(PlayableCardSubset::Six),
-            },
-            31 => Action {
-                play: Play::Play(PlayableCardSubset::Seven),
-            },
-            32 => Action {
-                play: Play::Play(PlayableCardSubset::Eight),
-            },
-            33 => Action {
-                play: Play::Play(PlayableCardSubset::Nine),
-            },
-            34 => Action {
-                play: Play::Play(PlayableCardSubset::Ten),
-            },
-            35 => Action {
-                play: Play::Play(PlayableCardSubset::Jack),
-            },
-            36 => Action {
-                play: Play::Play(PlayableCardSubset::Queen),
-            },
-            37 => Action {
-                play: Play::Play(PlayableCardSubset::King),
-            },
-            38 => Action {
-                play: Play::Play(PlayableCardSubset::Ace),
-            },
-            _ => panic!("Invalid action index: {}", val),
+        Action {
+            play: Play::from_index(val),
         }
     }
 }
 
-impl From<Action> for [f32; 39] {
+impl From<Action> for [f32; ACTION_SIZE] {
     fn from(val: Action) -> Self {
-        let mut output: [f32; 39] = [0.0; 39];
-        match val.play {
-            Play::Discard(Card::Joker) => output[0] = 1.0,
-            Play::Discard(Card::Two) => output[1] = 1.0,
-            Play::Discard(Card::Three) => output[2] = 1.0,
-            Play::Discard(Card::Four) => output[3] = 1.0,
-            Play::Discard(Card::Five) => output[4] = 1.0,
-            Play::Discard(Card::Six) => output[5] = 1.0,
-            Play::Discard(Card::Seven) => output[6] = 1.0,
-            Play::Discard(Card::Eight) => output[7] = 1.0,
-            Play::Discard(Card::Nine) => output[8] = 1.0,
-            Play::Discard(Card::Ten) => output[9] = 1.0,
-            Play::Discard(Card::Jack) => output[10] = 1.0,
-            Play::Discard(Card::Queen) => output[11] = 1.0,
-            Play::Discard(Card::King) => output[12] = 1.0,
-            Play::Discard(Card::Ace) => output[13] = 1.0,
-            Play::Draw => output[14] = 1.0,
-            Play::PickupPile => output[15] = 1.0,
-            Play::GoOut => output[16] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Four) => output[17] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Five) => output[18] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Six) => output[19] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Seven) => output[20] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Eight) => output[21] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Nine) => output[22] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Ten) => output[23] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Jack) => output[24] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Queen) => output[25] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::King) => output[26] = 1.0,
-            Play::PlaceWild(PlayableCardSubset::Ace) => output[27] = 1.0,
-            Play::Play(PlayableCardSubset::Four) => output[28] = 1.0,
-            Play::Play(PlayableCardSubset::Five) => output[29] = 1.0,
-            Play::Play(PlayableCardSubset::Six) => output[30] = 1.0,
-            Play::Play(PlayableCardSubset::Seven) => output[31] = 1.0,
-            Play::Play(PlayableCardSubset::Eight) => output[32] = 1.0,
-            Play::Play(PlayableCardSubset::Nine) => output[33] = 1.0,
-            Play::Play(PlayableCardSubset::Ten) => output[34] = 1.0,
-            Play::Play(PlayableCardSubset::Jack) => output[35] = 1.0,
-            Play::Play(PlayableCardSubset::Queen) => output[36] = 1.0,
-            Play::Play(PlayableCardSubset::King) => output[37] = 1.0,
-            Play::Play(PlayableCardSubset::Ace) => output[38] = 1.0,
-        }
+        let mut output: [f32; ACTION_SIZE] = [0.0; ACTION_SIZE];
+        output[val.play.index()] = 1.0;
         output
     }
 }
 
-impl From<[f32; 39]> for Action {
-    fn from(v: [f32; 39]) -> Self {
+impl From<[f32; ACTION_SIZE]> for Action {
+    fn from(v: [f32; ACTION_SIZE]) -> Self {
         //find max index in v
         let max_index = v
             .iter()
@@ -1676,122 +1684,178 @@ impl From<[f32; 39]> for Action {
             .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
             .unwrap()
             .0;
+        Action::from(max_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdp::Environment;
+    use env::CanastaEnv;
 
-        return match max_index {
-            0 => Action {
-                play: Play::Discard(Card::Joker),
-            },
-            1 => Action {
-                play: Play::Discard(Card::Two),
-            },
-            2 => Action {
-                play: Play::Discard(Card::Three),
-            },
-            3 => Action {
-                play: Play::Discard(Card::Four),
-            },
-            4 => Action {
-                play: Play::Discard(Card::Five),
-            },
-            5 => Action {
-                play: Play::Discard(Card::Six),
-            },
-            6 => Action {
-                play: Play::Discard(Card::Seven),
-            },
-            7 => Action {
-                play: Play::Discard(Card::Eight),
-            },
-            8 => Action {
-                play: Play::Discard(Card::Nine),
-            },
-            9 => Action {
-                play: Play::Discard(Card::Ten),
-            },
-            10 => Action {
-                play: Play::Discard(Card::Jack),
-            },
-            11 => Action {
-                play: Play::Discard(Card::Queen),
-            },
-            12 => Action {
-                play: Play::Discard(Card::King),
-            },
-            13 => Action {
-                play: Play::Discard(Card::Ace),
-            },
-            14 => Action { play: Play::Draw },
-            15 => Action {
-                play: Play::PickupPile,
-            },
-            16 => Action { play: Play::GoOut },
-            17 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Four),
-            },
-            18 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Five),
-            },
-            19 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Six),
-            },
-            20 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Seven),
-            },
-            21 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Eight),
-            },
-            22 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Nine),
-            },
-            23 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Ten),
-            },
-            24 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Jack),
-            },
-            25 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Queen),
-            },
-            26 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::King),
-            },
-            27 => Action {
-                play: Play::PlaceWild(PlayableCardSubset::Ace),
-            },
-            28 => Action {
-                play: Play::Play(PlayableCardSubset::Four),
-            },
-            29 => Action {
-                play: Play::Play(PlayableCardSubset::Five),
-            },
-            30 => Action {
-                play: Play::Play(PlayableCardSubset::Six),
-            },
-            31 => Action {
-                play: Play::Play(PlayableCardSubset::Seven),
-            },
-            32 => Action {
-                play: Play::Play(PlayableCardSubset::Eight),
-            },
-            33 => Action {
-                play: Play::Play(PlayableCardSubset::Nine),
-            },
-            34 => Action {
-                play: Play::Play(PlayableCardSubset::Ten),
-            },
-            35 => Action {
-                play: Play::Play(PlayableCardSubset::Jack),
-            },
-            36 => Action {
-                play: Play::Play(PlayableCardSubset::Queen),
-            },
-            37 => Action {
-                play: Play::Play(PlayableCardSubset::King),
-            },
-            38 => Action {
-                play: Play::Play(PlayableCardSubset::Ace),
-            },
-            _ => panic!("Invalid action index: {}", max_index),
+    #[test]
+    fn index_round_trip() {
+        for i in 0..ACTION_SIZE {
+            assert_eq!(Play::from_index(i).index(), i);
+            assert_eq!(usize::from(Action::from(i)), i);
+        }
+    }
+
+    #[test]
+    fn iterator_follows_index_order() {
+        assert_eq!(Play::iterator().len(), ACTION_SIZE);
+        for (i, play) in Play::iterator().enumerate() {
+            assert_eq!(play.index(), i);
+        }
+    }
+
+    #[test]
+    fn one_hot_round_trip() {
+        for play in Play::iterator() {
+            let one_hot: [f32; ACTION_SIZE] = Action { play: *play }.into();
+            assert_eq!(one_hot.iter().sum::<f32>(), 1.0);
+            assert_eq!(one_hot[play.index()], 1.0);
+            assert_eq!(Action::from(one_hot).play, *play);
+        }
+    }
+
+    #[test]
+    fn argmax_decodes_best_value() {
+        let mut values: [f32; ACTION_SIZE] = [-1.0; ACTION_SIZE];
+        values[Play::PickupPile.index()] = 3.0;
+        values[Play::Draw.index()] = 2.0;
+        values[Play::Discard(Card::Joker).index()] = f32::NEG_INFINITY;
+        assert_eq!(Action::from(values).play, Play::PickupPile);
+    }
+
+    #[test]
+    fn every_play_appears_once() {
+        for card in Card::iterator() {
+            assert!(Play::iterator().any(|play| *play == Play::Discard(*card)));
+        }
+        for card in PlayableCardSubset::iterator() {
+            assert!(Play::iterator().any(|play| *play == Play::Play(*card)));
+            assert!(Play::iterator().any(|play| *play == Play::PlaceWild(*card)));
+        }
+        for (i, play) in Play::iterator().enumerate() {
+            assert!(!Play::iterator().skip(i + 1).any(|other| other == play));
+        }
+    }
+
+    // Every card still in play: in the hands, the stock, the discard pile and on the boards
+    fn cards_in_play(game: &Game) -> u32 {
+        let mut count: u32 = game.draw_pile.cards.len() as u32 + game.discard_pile.len() as u32;
+        for player in game.players.iter() {
+            count += player.hand.get_hand_size() as u32;
+        }
+        for team in 0..game.teams_count {
+            let board = game.players[team as usize].board.lock().unwrap();
+            for stack in board.piles.iter().flatten() {
+                count += (stack.card_count + stack.jokers + stack.twos) as u32;
+            }
+        }
+        count
+    }
+
+    // A game played with the last legal action until a board has melds and a seat other than the
+    // one to play has revealed cards from a pile pickup
+    fn game_in_progress() -> Game {
+        let mut env: CanastaEnv<2, 2> = CanastaEnv::new();
+        env.reset(Some(3));
+        loop {
+            let game = env.game();
+            let seat = game.turn.get() as usize;
+            let revealed = (game.players.iter().enumerate())
+                .any(|(i, player)| i != seat && player.revealed.iter().sum::<u8>() > 0);
+            let melded = (0..game.teams_count).any(|team| {
+                let board = game.players[team as usize].board.lock().unwrap();
+                board.piles.iter().flatten().next().is_some()
+            });
+            if revealed && melded {
+                return game.clone();
+            }
+            assert!(!game.finished, "The game ended before any pickup");
+            let action = env.legal_actions().last().unwrap().clone();
+            env.step(&action);
+        }
+    }
+
+    #[test]
+    fn determinize_keeps_what_the_seat_knows() {
+        let game = game_in_progress();
+        let seat = game.turn.get();
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..5 {
+            let sample = game.determinize(seat, &mut rng);
+            for (player, original) in sample.players.iter().zip(game.players.iter()) {
+                assert_eq!(player.hand.get_hand_size(), original.hand.get_hand_size());
+                assert_eq!(player.revealed, original.revealed);
+                for (held, revealed) in player.hand.hand.iter().zip(original.revealed) {
+                    assert!(*held >= revealed);
+                }
+                assert_eq!(*player.board.lock().unwrap(), *original.board.lock().unwrap());
+            }
+            assert_eq!(
+                sample.players[seat as usize].hand,
+                game.players[seat as usize].hand
+            );
+            assert_eq!(sample.discard_pile, game.discard_pile);
+            assert_eq!(cards_in_play(&sample), cards_in_play(&game));
+        }
+    }
+
+    #[test]
+    fn actions_are_the_legal_ones() {
+        let mut env: CanastaEnv<2, 2> = CanastaEnv::new();
+        let mut state = env.reset(Some(8));
+        for _ in 0..50 {
+            let actions = state.actions();
+            let mask = state.legal_mask();
+            assert_eq!(actions.len(), mask.iter().filter(|legal| **legal).count());
+            for action in actions.iter() {
+                assert!(mask[usize::from(action.clone())]);
+                assert!(state.check_legal_action(action.clone()));
+            }
+            state = env.step(&actions[0]).0;
+        }
+    }
+
+    #[test]
+    fn seed_is_left_out_of_equality() {
+        use std::collections::hash_map::DefaultHasher;
+        let hash = |game: &Game| {
+            let mut hasher = DefaultHasher::new();
+            game.hash(&mut hasher);
+            hasher.finish()
         };
+        let game = Game::new_seeded(2, 2, 2, 11, 1);
+        let mut other = game.detached_clone();
+        other.seed = 2;
+        assert_eq!(game, other);
+        assert_eq!(hash(&game), hash(&other));
+        assert_ne!(game, Game::new_seeded(2, 2, 2, 11, 2));
+    }
+
+    #[test]
+    fn deck_composition_matches_the_draw_pile() {
+        for decks in 1..=3 {
+            let mut counts: [u8; 14] = [0; 14];
+            for card in DrawPile::new(decks).cards {
+                counts[card.get_index()] += 1;
+            }
+            assert_eq!(DrawPile::composition(decks), counts);
+        }
+    }
+
+    #[test]
+    fn names_are_unique() {
+        assert_eq!(Play::Discard(Card::Joker).name(), "Discard Joker");
+        assert_eq!(Play::Draw.name(), "Draw");
+        assert_eq!(Play::PlaceWild(PlayableCardSubset::Seven).name(), "PlaceWild Seven");
+        assert_eq!(Play::Play(PlayableCardSubset::Ace).name(), "Play Ace");
+        for (i, play) in Play::iterator().enumerate() {
+            assert!(!Play::iterator().skip(i + 1).any(|other| other.name() == play.name()));
+        }
     }
 }
@@ -0,0 +1,289 @@
+//! Card-level beliefs about the hidden parts of a `Game`, as seen from a single seat.
+//!
+//! A seat sees its own hand, both boards, the discard pile and every card another player picked
+//! up from the discard pile. Everything else is "unseen": it is either in the stock or among the
+//! cards of another hand that nobody has seen. Without further information every arrangement of
+//! the unseen cards is equally likely, so the unknown part of each hand is a uniform draw from
+//! the unseen pool.
+
+use super::{
+    Card, DrawPile, Game, GameState, CARD_KINDS, PLAYERS_PER_TEAM, STATE_SIZE, TEAMS_COUNT,
+};
+
+/// Size of the belief encoder block: the unseen pool plus the expected hand of every other seat.
+pub const BELIEF_SIZE: usize = CARD_KINDS * (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize;
+/// Size of the standard state encoding followed by the belief block.
+pub const BELIEF_STATE_SIZE: usize = STATE_SIZE + BELIEF_SIZE;
+
+/// What a seat knows about the hand of one other player.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HandBelief {
+    /// The player this belief is about.
+    pub player: u8,
+    /// Cards known to be in the hand, by card index.
+    pub known: [u8; CARD_KINDS],
+    /// Number of cards in the hand that are not accounted for by `known`.
+    pub unknown: u8,
+}
+
+/// The belief of one seat about the cards it cannot see.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Belief {
+    /// The seat holding this belief.
+    pub seat: u8,
+    /// Cards whose location is unknown to the seat, by card index.
+    pub unseen: [u8; CARD_KINDS],
+    /// Number of cards left in the stock.
+    pub stock: u8,
+    /// One entry for every other seat, in turn order starting after `seat`.
+    pub hands: Vec<HandBelief>,
+}
+
+impl Belief {
+    /// Computes the belief of `seat` from what it can observe in `game`.
+    pub fn observe(game: &Game, seat: u8) -> Belief {
+        let players_count = game.teams_count * game.players_per_team;
+        let mut unseen = DrawPile::composition(game.decks);
+
+        remove(&mut unseen, &game.players[seat as usize].hand.hand);
+        for card in game.discard_pile.iter() {
+            let mut counts: [u8; CARD_KINDS] = [0; CARD_KINDS];
+            counts[card.get_index()] = 1;
+            remove(&mut unseen, &counts);
+        }
+        for team in 0..game.teams_count {
+            let board = game.players[team as usize].board.lock().unwrap();
+            for stack in board.piles.iter().flatten() {
+                let mut counts: [u8; CARD_KINDS] = [0; CARD_KINDS];
+                counts[stack.card_type.get_index()] += stack.card_count;
+                counts[Card::Joker.get_index()] += stack.jokers;
+                counts[Card::Two.get_index()] += stack.twos;
+                remove(&mut unseen, &counts);
+            }
+        }
+
+        let mut hands: Vec<HandBelief> = Vec::new();
+        for i in 1..players_count {
+            let player_index = (seat + i) % players_count;
+            let player = &game.players[player_index as usize];
+            remove(&mut unseen, &player.revealed);
+            let revealed: u8 = player.revealed.iter().sum();
+            debug_assert!(
+                revealed <= player.hand.get_hand_size(),
+                "More cards revealed than held"
+            );
+            hands.push(HandBelief {
+                player: player_index,
+                known: player.revealed,
+                unknown: player.hand.get_hand_size().saturating_sub(revealed),
+            });
+        }
+
+        Belief {
+            seat,
+            unseen,
+            stock: game.draw_pile.cards.len() as u8,
+            hands,
+        }
+    }
+
+    /// Total number of unseen cards.
+    pub fn unseen_total(&self) -> u16 {
+        self.unseen.iter().map(|c| *c as u16).sum()
+    }
+
+    /// The belief about `player`'s hand. Panics if `player` is the seat holding this belief.
+    pub fn hand(&self, player: u8) -> &HandBelief {
+        self.hands
+            .iter()
+            .find(|hand| hand.player == player)
+            .expect("No belief is held about the seat's own hand")
+    }
+
+    /// Probability that `player` holds exactly `k` copies of `card`, for every possible `k`.
+    pub fn count_distribution(&self, player: u8, card: Card) -> Vec<f64> {
+        let hand = self.hand(player);
+        let known = hand.known[card.get_index()] as usize;
+        let copies = self.unseen[card.get_index()] as u64;
+        let total = self.unseen_total() as u64;
+        let draws = hand.unknown as u64;
+
+        // The unknown cards are a uniform draw from the unseen pool (hypergeometric)
+        let mut distribution: Vec<f64> = vec![0.0; known + copies.min(draws) as usize + 1];
+        let all = choose(total, draws);
+        for k in 0..=copies.min(draws) {
+            distribution[known + k as usize] =
+                choose(copies, k) * choose(total - copies, draws - k) / all;
+        }
+        distribution
+    }
+
+    /// Probability that `player` holds at least one copy of `card`.
+    pub fn holds_probability(&self, player: u8, card: Card) -> f64 {
+        1.0 - self.count_distribution(player, card)[0]
+    }
+
+    /// Expected number of copies of every card in `player`'s hand, by card index.
+    pub fn expected_hand(&self, player: u8) -> [f32; CARD_KINDS] {
+        let hand = self.hand(player);
+        let total = self.unseen_total() as f32;
+        let mut expected: [f32; CARD_KINDS] = [0.0; CARD_KINDS];
+        for (i, expected) in expected.iter_mut().enumerate() {
+            *expected = hand.known[i] as f32;
+            if total > 0.0 {
+                *expected += hand.unknown as f32 * self.unseen[i] as f32 / total;
+            }
+        }
+        expected
+    }
+}
+
+impl From<Belief> for [f32; BELIEF_SIZE] {
+    fn from(belief: Belief) -> Self {
+        let mut output: [f32; BELIEF_SIZE] = [0.0; BELIEF_SIZE];
+        //Unseen cards
+        for (output, unseen) in output.iter_mut().zip(belief.unseen) {
+            *output = unseen as f32;
+        }
+        //Expected hands of the other seats
+        for (i, hand) in belief.hands.iter().enumerate() {
+            let expected = belief.expected_hand(hand.player);
+            output[CARD_KINDS * (i + 1)..CARD_KINDS * (i + 2)].copy_from_slice(&expected);
+        }
+        output
+    }
+}
+
+impl From<GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>> for [f32; BELIEF_STATE_SIZE] {
+    fn from(state: GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>) -> Self {
+        let mut output: [f32; BELIEF_STATE_SIZE] = [0.0; BELIEF_STATE_SIZE];
+        let belief = state.game.belief(state.game.turn.get());
+        let base: [f32; STATE_SIZE] = state.into();
+        let block: [f32; BELIEF_SIZE] = belief.into();
+        output[..STATE_SIZE].copy_from_slice(&base);
+        output[STATE_SIZE..].copy_from_slice(&block);
+        output
+    }
+}
+
+impl Game {
+    /// The belief `seat` holds about the cards it cannot see.
+    pub fn belief(&self, seat: u8) -> Belief {
+        Belief::observe(self, seat)
+    }
+}
+
+fn remove(unseen: &mut [u8; CARD_KINDS], counts: &[u8; CARD_KINDS]) {
+    for (unseen, count) in unseen.iter_mut().zip(counts) {
+        debug_assert!(*unseen >= *count, "More cards seen than exist");
+        *unseen = unseen.saturating_sub(*count);
+    }
+}
+
+fn choose(n: u64, k: u64) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    let mut out: f64 = 1.0;
+    for i in 0..k.min(n - k) {
+        out *= (n - i) as f64 / (i + 1) as f64;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canastautil::env::CanastaEnv;
+    use crate::canastautil::Play;
+    use crate::mdp::Environment;
+
+    // A freshly dealt game, where the next seat is marked as having revealed a card of its hand
+    fn game_with_revealed() -> (Game, u8, usize) {
+        let mut game = Game::new_seeded(2, 2, 2, 11, 5);
+        let other = 1;
+        let card = (0..CARD_KINDS)
+            .find(|i| game.players[other as usize].hand.hand[*i] > 0)
+            .unwrap();
+        game.players[other as usize].revealed[card] = 1;
+        (game, other, card)
+    }
+
+    #[test]
+    fn count_distributions_sum_to_one() {
+        let (game, _, _) = game_with_revealed();
+        let belief = game.belief(0);
+        for hand in belief.hands.iter() {
+            for card in Card::iterator() {
+                let distribution = belief.count_distribution(hand.player, *card);
+                let total: f64 = distribution.iter().sum();
+                assert!((total - 1.0).abs() < 1e-9, "{:?}: {}", card, total);
+            }
+        }
+    }
+
+    #[test]
+    fn expected_hands_total_the_hand_sizes() {
+        let (game, _, _) = game_with_revealed();
+        let belief = game.belief(0);
+        for hand in belief.hands.iter() {
+            let size = game.players[hand.player as usize].hand.get_hand_size();
+            let total: f32 = belief.expected_hand(hand.player).iter().sum();
+            assert!((total - size as f32).abs() < 1e-4);
+        }
+        let unseen = belief.unseen_total();
+        let unknown: u16 = belief.hands.iter().map(|hand| hand.unknown as u16).sum();
+        assert_eq!(unseen, unknown + belief.stock as u16);
+    }
+
+    #[test]
+    fn revealed_cards_stay_fixed() {
+        let (game, other, card) = game_with_revealed();
+        let belief = game.belief(0);
+        let hand = belief.hand(other);
+        assert_eq!(hand.known[card], 1);
+        // Holding the revealed copy for sure, and perhaps more from the unseen pool
+        let distribution = belief.count_distribution(other, Card::from_index(card));
+        assert_eq!(distribution[0], 0.0);
+        assert_eq!(belief.holds_probability(other, Card::from_index(card)), 1.0);
+        assert!(belief.expected_hand(other)[card] >= 1.0);
+
+        let mut hidden = game_with_revealed().0;
+        hidden.players[other as usize].revealed = [0; CARD_KINDS];
+        assert_eq!(hidden.belief(0).unseen[card], belief.unseen[card] + 1);
+    }
+
+    #[test]
+    fn pickups_are_tracked_until_the_cards_are_played() {
+        // Playing the last legal action picks up the pile whenever it may
+        let mut env: CanastaEnv<2, 2> = CanastaEnv::new();
+        env.reset(Some(3));
+        let mut picked_up: Option<usize> = None;
+        loop {
+            let game = env.game();
+            assert!(!game.finished, "No picked up card was played");
+            let seat = game.turn.get() as usize;
+            let action = env.legal_actions().last().unwrap().clone();
+            let before = game.players[seat].revealed;
+            env.step(&action);
+            let game = env.game();
+            let after = game.players[seat].revealed;
+
+            let observer = ((seat + 1) % 4) as u8;
+            let belief = game.belief(observer);
+            assert_eq!(belief.hand(seat as u8).known, after);
+            let unknown: u16 = belief.hands.iter().map(|hand| hand.unknown as u16).sum();
+            assert_eq!(belief.unseen_total(), unknown + belief.stock as u16);
+
+            if action.play == Play::PickupPile {
+                assert!(after.iter().sum::<u8>() > before.iter().sum::<u8>());
+                picked_up = Some(seat);
+            } else if picked_up == Some(seat) && after.iter().sum::<u8>() < before.iter().sum() {
+                // The played cards are on the board or the discard pile, no longer in the hand
+                let hidden = game.players[seat].hand.get_hand_size() - after.iter().sum::<u8>();
+                assert_eq!(belief.hand(seat as u8).unknown, hidden);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Canasta as an `Environment`: one value drives every seat of a game, in turn.
+
+use super::{Action, Game, GameState, DECKS, HAND_SIZE};
+use crate::mdp::Environment;
+use rand::{thread_rng, Rng};
+
+/// What `CanastaEnv::step` tells besides the observation, reward and end of episode.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepInfo {
+    /// The seat that acted.
+    pub player: usize,
+    /// Whether the action ended the seat's turn, i.e. it was a discard or going out.
+    pub turn_ended: bool,
+    /// Rewards by seat, as given by `Game::rewards`: only non-zero once the episode is over.
+    pub rewards: Vec<f64>,
+}
+
+/// A game of Canasta between `PLAYERS_PER_TEAM * TEAMS_COUNT` seats.
+pub struct CanastaEnv<const PLAYERS_PER_TEAM: u8, const TEAMS_COUNT: u8> {
+    game: Game,
+}
+
+impl<const PLAYERS_PER_TEAM: u8, const TEAMS_COUNT: u8> CanastaEnv<PLAYERS_PER_TEAM, TEAMS_COUNT> {
+    /// Creates an environment; call `reset` to deal a game with a chosen seed.
+    pub fn new() -> Self {
+        Self {
+            game: Game::new(TEAMS_COUNT, PLAYERS_PER_TEAM, DECKS, HAND_SIZE),
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    // Boards are shared between the seats of a team, so a plain clone would see later melds
+    fn observe(&self) -> GameState<PLAYERS_PER_TEAM, TEAMS_COUNT> {
+        GameState {
+            game: self.game.detached_clone(),
+        }
+    }
+}
+
+impl<const PLAYERS_PER_TEAM: u8, const TEAMS_COUNT: u8> Default
+    for CanastaEnv<PLAYERS_PER_TEAM, TEAMS_COUNT>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const PLAYERS_PER_TEAM: u8, const TEAMS_COUNT: u8> Environment
+    for CanastaEnv<PLAYERS_PER_TEAM, TEAMS_COUNT>
+{
+    type Obs = GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>;
+    type A = Action;
+    type Info = StepInfo;
+
+    fn reset(&mut self, seed: Option<u64>) -> Self::Obs {
+        self.game = Game::new_seeded(
+            TEAMS_COUNT,
+            PLAYERS_PER_TEAM,
+            DECKS,
+            HAND_SIZE,
+            seed.unwrap_or_else(|| thread_rng().gen()),
+        );
+        self.observe()
+    }
+
+    /// Panics if `action` is not legal, or if the game is over.
+    fn step(&mut self, action: &Action) -> (Self::Obs, f64, bool, StepInfo) {
+        assert!(!self.game.finished, "The game is over");
+        assert!(
+            self.game.check_legal(action.play),
+            "{} is not legal",
+            action.play.name()
+        );
+        let player = self.current_player();
+        let turn_before = self.game.get_total_turns();
+        self.game.execute_play(action.play);
+        let rewards = self.game.rewards();
+        let info = StepInfo {
+            player,
+            turn_ended: self.game.finished || self.game.get_total_turns() != turn_before,
+            rewards,
+        };
+        (
+            self.observe(),
+            info.rewards[player],
+            self.game.finished,
+            info,
+        )
+    }
+
+    fn current_player(&self) -> usize {
+        self.game.turn.get() as usize
+    }
+
+    fn legal_actions(&self) -> Vec<Action> {
+        self.game
+            .legal_mask()
+            .iter()
+            .enumerate()
+            .filter(|(_, legal)| **legal)
+            .map(|(i, _)| Action::from(i))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canastautil::STATE_SIZE;
+    use crate::mdp::VecEnv;
+
+    // Plays the first legal action until the end, returning the final rewards
+    fn play_out(env: &mut CanastaEnv<2, 2>) -> Vec<f64> {
+        loop {
+            let player = env.current_player();
+            let action = env.legal_actions()[0].clone();
+            let (obs, reward, done, info) = env.step(&action);
+            assert_eq!(info.player, player);
+            assert_eq!(obs.game, *env.game());
+            assert_eq!(reward, info.rewards[player]);
+            if done {
+                assert!(info.turn_ended);
+                return info.rewards;
+            }
+            assert_eq!(reward, 0.0);
+        }
+    }
+
+    #[test]
+    fn episodes_follow_the_seed() {
+        let mut env: CanastaEnv<2, 2> = CanastaEnv::new();
+        let first = env.reset(Some(11));
+        let rewards = play_out(&mut env);
+        assert_eq!(rewards, env.game().rewards());
+        assert_eq!(rewards.iter().sum::<f64>(), 0.0);
+
+        assert_eq!(env.reset(Some(11)).game, first.game);
+        assert_eq!(play_out(&mut env), rewards);
+    }
+
+    #[test]
+    fn vec_env_steps_every_game() {
+        let mut envs: VecEnv<CanastaEnv<2, 2>, 3> =
+            VecEnv::new(Default::default(), [Some(1), Some(2), Some(3)]);
+        let mut alone: CanastaEnv<2, 2> = CanastaEnv::new();
+        alone.reset(Some(2));
+        for _ in 0..20 {
+            let actions = envs
+                .envs()
+                .each_ref()
+                .map(|env| env.legal_actions()[0].clone());
+            let players = envs.current_players();
+            let results = envs.step(&actions);
+            let (_, _, _, info) = alone.step(&actions[1]);
+            assert_eq!(results[1].2, info);
+            for (i, (_, _, info)) in results.iter().enumerate() {
+                assert_eq!(info.player, players[i]);
+                assert_eq!(envs.observations()[i].game, *envs.envs()[i].game());
+            }
+        }
+        assert_eq!(envs.envs()[1].game(), alone.game());
+
+        envs.reset(1, Some(3));
+        alone.reset(Some(3));
+        assert_eq!(envs.envs()[1].game(), alone.game());
+    }
+
+    #[test]
+    fn observations_do_not_change_after_the_step() {
+        let mut env: CanastaEnv<2, 2> = CanastaEnv::new();
+        let kept = env.reset(Some(3));
+        let encoded: [f32; STATE_SIZE] = kept.clone().into();
+        let melds = |env: &CanastaEnv<2, 2>| -> usize {
+            env.game().table().teams.iter().map(|team| team.melds.len()).sum()
+        };
+        while melds(&env) == 0 {
+            let action = env.legal_actions().pop().unwrap();
+            env.step(&action);
+        }
+        let now: [f32; STATE_SIZE] = kept.into();
+        assert_eq!(now, encoded);
+    }
+}
@@ -0,0 +1,325 @@
+//! Compact game notation and the game record format.
+//!
+//! Plays are written as `D` (draw), `P` (pick up the pile), `M:K` (meld a King), `W:7` (place a
+//! wild card on the Sevens), `X:5` (discard a Five) and `OUT` (go out). Cards are written as
+//! `JK`, `2`, `3`, ..., `10`, `J`, `Q`, `K` and `A`.
+//!
+//! A game record lists the rules and the seed of the deal, who sat where and every play in
+//! order, one turn per line:
+//!
+//! ```text
+//! [Canasta]
+//! Teams: 2
+//! PlayersPerTeam: 2
+//! Decks: 2
+//! HandSize: 13
+//! Seed: 8364118613468154473
+//! Seat 0: TrainedAgent
+//! Seat 1: RandomAgent
+//! Seat 2: TrainedAgent
+//! Seat 3: RandomAgent
+//! Moves:
+//! D X:5
+//! P M:K M:K X:7
+//! Result: 120 -40 120 -40
+//! ```
+//!
+//! Since the deal only depends on the seed, a record is turned back into a `Game` by replaying
+//! its plays.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Card, Game, Play, PlayableCardSubset};
+
+impl Card {
+    /// The notation of this card.
+    pub fn notation(&self) -> &'static str {
+        match self {
+            Card::Joker => "JK",
+            Card::Two => "2",
+            Card::Three => "3",
+            Card::Four => "4",
+            Card::Five => "5",
+            Card::Six => "6",
+            Card::Seven => "7",
+            Card::Eight => "8",
+            Card::Nine => "9",
+            Card::Ten => "10",
+            Card::Jack => "J",
+            Card::Queen => "Q",
+            Card::King => "K",
+            Card::Ace => "A",
+        }
+    }
+}
+
+impl FromStr for Card {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Card, String> {
+        let s = s.trim().to_uppercase();
+        for card in Card::iterator() {
+            if card.notation() == s {
+                return Ok(*card);
+            }
+        }
+        Err(format!("Unknown card: {}", s))
+    }
+}
+
+impl fmt::Display for Play {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Play::Draw => write!(f, "D"),
+            Play::PickupPile => write!(f, "P"),
+            Play::GoOut => write!(f, "OUT"),
+            Play::Play(card) => write!(f, "M:{}", Card::from(*card).notation()),
+            Play::PlaceWild(card) => write!(f, "W:{}", Card::from(*card).notation()),
+            Play::Discard(card) => write!(f, "X:{}", card.notation()),
+        }
+    }
+}
+
+impl FromStr for Play {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Play, String> {
+        let s = s.trim().to_uppercase();
+        match s.split_once(':') {
+            None => match s.as_str() {
+                "D" => Ok(Play::Draw),
+                "P" => Ok(Play::PickupPile),
+                "OUT" => Ok(Play::GoOut),
+                _ => Err(format!("Unknown play: {}", s)),
+            },
+            Some(("X", card)) => Ok(Play::Discard(card.parse()?)),
+            Some((kind, card)) => {
+                let card: Card = card.parse()?;
+                let subset = match PlayableCardSubset::iterator().find(|c| Card::from(**c) == card)
+                {
+                    Some(subset) => *subset,
+                    None => return Err(format!("{} can not be melded: {}", card, s)),
+                };
+                match kind {
+                    "M" => Ok(Play::Play(subset)),
+                    "W" => Ok(Play::PlaceWild(subset)),
+                    _ => Err(format!("Unknown play: {}", s)),
+                }
+            }
+        }
+    }
+}
+
+/// Everything needed to reproduce a game: its rules, the seed of the deal, the seats and the
+/// plays made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameRecord {
+    pub teams_count: u8,
+    pub players_per_team: u8,
+    pub decks: u8,
+    pub hand_size: u8,
+    pub seed: u64,
+    /// A description of who played at each seat, e.g. the agent type.
+    pub seats: Vec<String>,
+    pub plays: Vec<Play>,
+    /// Final scores by seat, once the game is over.
+    pub result: Option<Vec<i16>>,
+}
+
+impl GameRecord {
+    /// Starts a record for `game`, which must not have been played on yet.
+    pub fn new(game: &Game, seats: Vec<String>) -> GameRecord {
+        GameRecord {
+            teams_count: game.teams_count,
+            players_per_team: game.players_per_team,
+            decks: game.decks,
+            hand_size: game.hand_size,
+            seed: game.seed,
+            seats,
+            plays: Vec::new(),
+            result: None,
+        }
+    }
+
+    pub fn push(&mut self, play: Play) {
+        self.plays.push(play);
+    }
+
+    /// Records the scores of the finished `game`.
+    pub fn finish(&mut self, game: &Game) {
+        self.result = Some(game.get_scores());
+    }
+
+    /// Replays the first `moves` plays, returning the game as it was after them.
+    pub fn replay_to(&self, moves: usize) -> Result<Game, String> {
+        let mut game = Game::new_seeded(
+            self.teams_count,
+            self.players_per_team,
+            self.decks,
+            self.hand_size,
+            self.seed,
+        );
+        for (i, play) in self.plays.iter().take(moves).enumerate() {
+            if game.finished {
+                return Err(format!("Move {} ({}) comes after the end of the game", i + 1, play));
+            }
+            if !game.check_legal(*play) {
+                return Err(format!("Move {} ({}) is illegal", i + 1, play));
+            }
+            game.execute_play(*play);
+        }
+        Ok(game)
+    }
+
+    /// Replays every play of the record.
+    pub fn replay(&self) -> Result<Game, String> {
+        self.replay_to(self.plays.len())
+    }
+
+    /// Parses every record in `s`, e.g. the contents of a file records were appended to. A record
+    /// starts at a line holding only the `[Canasta]` header, so seat names may contain it.
+    pub fn parse_all(s: &str) -> Result<Vec<GameRecord>, String> {
+        let mut records: Vec<String> = Vec::new();
+        for line in s.lines() {
+            if records.is_empty() || line.trim() == "[Canasta]" {
+                records.push(String::new());
+            }
+            let record = records.last_mut().unwrap();
+            record.push_str(line);
+            record.push('\n');
+        }
+        records
+            .iter()
+            .filter(|record| !record.trim().is_empty())
+            .map(|record| record.parse())
+            .collect()
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[Canasta]")?;
+        writeln!(f, "Teams: {}", self.teams_count)?;
+        writeln!(f, "PlayersPerTeam: {}", self.players_per_team)?;
+        writeln!(f, "Decks: {}", self.decks)?;
+        writeln!(f, "HandSize: {}", self.hand_size)?;
+        writeln!(f, "Seed: {}", self.seed)?;
+        for (i, seat) in self.seats.iter().enumerate() {
+            writeln!(f, "Seat {}: {}", i, seat)?;
+        }
+        writeln!(f, "Moves:")?;
+        let mut turn: Vec<String> = Vec::new();
+        for play in self.plays.iter() {
+            turn.push(play.to_string());
+            // A turn ends with a discard or with going out
+            if matches!(play, Play::Discard(_) | Play::GoOut) {
+                writeln!(f, "{}", turn.join(" "))?;
+                turn.clear();
+            }
+        }
+        if !turn.is_empty() {
+            writeln!(f, "{}", turn.join(" "))?;
+        }
+        if let Some(result) = &self.result {
+            let scores: Vec<String> = result.iter().map(|s| s.to_string()).collect();
+            writeln!(f, "Result: {}", scores.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<GameRecord, String> {
+        let mut lines = s.lines().map(|line| line.trim()).filter(|line| !line.is_empty());
+        if lines.clone().next() == Some("[Canasta]") {
+            lines.next();
+        }
+        let mut field = |name: &str| -> Result<String, String> {
+            match lines.next().and_then(|line| line.split_once(':')) {
+                Some((key, value)) if key.trim() == name => Ok(value.trim().to_string()),
+                _ => Err(format!("Expected {}", name)),
+            }
+        };
+        let mut record = GameRecord {
+            teams_count: number(field("Teams")?)?,
+            players_per_team: number(field("PlayersPerTeam")?)?,
+            decks: number(field("Decks")?)?,
+            hand_size: number(field("HandSize")?)?,
+            seed: number(field("Seed")?)?,
+            seats: Vec::new(),
+            plays: Vec::new(),
+            result: None,
+        };
+        for i in 0..record.teams_count * record.players_per_team {
+            record.seats.push(field(&format!("Seat {}", i))?);
+        }
+        field("Moves")?;
+        for line in lines {
+            if let Some(result) = line.strip_prefix("Result:") {
+                let scores: Result<Vec<i16>, String> = result
+                    .split_whitespace()
+                    .map(|score| score.parse().map_err(|_| format!("Invalid score: {}", score)))
+                    .collect();
+                record.result = Some(scores?);
+                break;
+            }
+            for play in line.split_whitespace() {
+                record.plays.push(play.parse()?);
+            }
+        }
+        Ok(record)
+    }
+}
+
+fn number<T: FromStr>(value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_notation_round_trip() {
+        for play in Play::iterator() {
+            assert_eq!(play.to_string().parse::<Play>(), Ok(*play));
+        }
+        assert_eq!("m:k".parse::<Play>(), Ok(Play::Play(PlayableCardSubset::King)));
+        assert_eq!("X:JK".parse::<Play>(), Ok(Play::Discard(Card::Joker)));
+        assert!("M:3".parse::<Play>().is_err());
+        assert!("Y:K".parse::<Play>().is_err());
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let mut game = Game::new_seeded(2, 2, 2, 13, 21);
+        let mut seats: Vec<String> = (0..4).map(|i| format!("Agent{}", i)).collect();
+        seats[3] = "Bot [Canasta] 2".to_string();
+        let mut record = GameRecord::new(&game, seats);
+        while !game.finished {
+            // The first legal play, so that the game is the same on every run
+            let play = *Play::iterator()
+                .zip(game.legal_mask())
+                .find(|(_, legal)| *legal)
+                .unwrap()
+                .0;
+            record.push(play);
+            game.execute_play(play);
+        }
+        record.finish(&game);
+
+        let parsed: GameRecord = record.to_string().parse().unwrap();
+        assert_eq!(parsed, record);
+        let replayed = parsed.replay().unwrap();
+        assert_eq!(replayed, game);
+        assert_eq!(Some(replayed.get_scores()), record.result);
+
+        let both = format!("{}\n{}", record, record);
+        assert_eq!(GameRecord::parse_all(&both).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,132 @@
+//! A snapshot of everything on the table, for front ends that draw a `Game`.
+
+use super::{BoardStack, Card, Game};
+
+/// One meld on a team's board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Meld {
+    pub card: Card,
+    /// Number of natural cards in the meld.
+    pub naturals: u8,
+    pub jokers: u8,
+    pub twos: u8,
+    /// Whether the board counts the meld as a canasta.
+    pub canasta: bool,
+}
+
+impl Meld {
+    /// Number of wild cards in the meld.
+    pub fn wilds(&self) -> u8 {
+        self.jokers + self.twos
+    }
+
+    /// Whether the meld is made of natural cards only.
+    pub fn is_natural(&self) -> bool {
+        self.wilds() == 0
+    }
+}
+
+impl From<BoardStack> for Meld {
+    fn from(stack: BoardStack) -> Self {
+        Meld {
+            card: stack.card_type,
+            naturals: stack.card_count,
+            jokers: stack.jokers,
+            twos: stack.twos,
+            canasta: stack.is_canasta(),
+        }
+    }
+}
+
+/// A seat and the cards in its hand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeatTable {
+    pub seat: u8,
+    pub team: u8,
+    /// The cards in the hand, in card index order.
+    pub hand: Vec<Card>,
+    /// The cards of the hand every seat has seen, i.e. those picked up from the discard pile.
+    pub known: Vec<Card>,
+}
+
+/// A team's board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TeamTable {
+    pub team: u8,
+    /// The melds on the board, in card index order.
+    pub melds: Vec<Meld>,
+    /// Points on the board, not counting the cards left in hand.
+    pub score: u16,
+}
+
+/// Everything on the table at one point of a game, hands included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table {
+    /// The seat to play.
+    pub turn: u8,
+    pub total_turns: u16,
+    /// Whether the seat to play has drawn this turn.
+    pub has_drawn: bool,
+    pub finished: bool,
+    /// Number of cards left in the stock.
+    pub stock: u8,
+    /// The discard pile, top card last.
+    pub discard_pile: Vec<Card>,
+    pub frozen: bool,
+    pub seats: Vec<SeatTable>,
+    pub teams: Vec<TeamTable>,
+}
+
+impl Game {
+    /// A snapshot of the table.
+    pub fn table(&self) -> Table {
+        let seats = self
+            .players
+            .iter()
+            .enumerate()
+            .map(|(i, player)| SeatTable {
+                seat: i as u8,
+                team: i as u8 % self.teams_count,
+                hand: cards(&player.hand.hand),
+                known: cards(&player.revealed),
+            })
+            .collect();
+        let teams = (0..self.teams_count)
+            .map(|team| {
+                let board = self.players[team as usize].board.lock().unwrap();
+                TeamTable {
+                    team,
+                    melds: board
+                        .piles
+                        .iter()
+                        .flatten()
+                        .map(|stack| Meld::from(*stack))
+                        .collect(),
+                    score: board.get_score(),
+                }
+            })
+            .collect();
+        Table {
+            turn: self.turn.get(),
+            total_turns: self.turn.total_turns,
+            has_drawn: self.curr_player_drawn,
+            finished: self.finished,
+            stock: self.draw_pile.cards.len() as u8,
+            discard_pile: self.discard_pile.clone(),
+            frozen: self.frozen,
+            seats,
+            teams,
+        }
+    }
+}
+
+// Expands counts by card index into a list of cards
+fn cards(counts: &[u8; 14]) -> Vec<Card> {
+    let mut cards: Vec<Card> = Vec::new();
+    for (index, count) in counts.iter().enumerate() {
+        for _ in 0..*count {
+            cards.push(Card::from_index(index));
+        }
+    }
+    cards
+}
@@ -0,0 +1,249 @@
+//! C API for embedding the engine, built with the `capi` feature.
+//!
+//! The build writes the matching header to `include/canasta.h`. Games are opaque handles created
+//! with `canasta_game_new` and released with `canasta_game_free`. Functions filling a buffer take
+//! its length and fail with `CANASTA_STATUS_BUFFER_TOO_SMALL` if it can not hold the result.
+
+use std::slice;
+
+use crate::canastautil::{
+    self, Action, Game, GameState, ACTION_SIZE, PLAYERS_PER_TEAM, STATE_SIZE, TEAMS_COUNT,
+};
+
+/// Length of the observation written by `canasta_observation`.
+pub const CANASTA_STATE_SIZE: usize = 190;
+/// Number of actions, i.e. the length of the legal mask.
+pub const CANASTA_ACTION_SIZE: usize = 39;
+/// Number of seats, i.e. the number of scores.
+pub const CANASTA_PLAYERS: usize = 4;
+
+const _: () = assert!(CANASTA_STATE_SIZE == STATE_SIZE);
+const _: () = assert!(CANASTA_ACTION_SIZE == ACTION_SIZE);
+const _: () = assert!(CANASTA_PLAYERS == (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize);
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum CanastaStatus {
+    Ok = 0,
+    NullPointer = 1,
+    BufferTooSmall = 2,
+    InvalidAction = 3,
+    IllegalAction = 4,
+    GameOver = 5,
+}
+
+/// A game between four seats, partners sitting opposite each other.
+pub struct CanastaGame {
+    game: Game,
+}
+
+fn deal(seed: u64) -> Game {
+    Game::new_seeded(
+        TEAMS_COUNT,
+        PLAYERS_PER_TEAM,
+        canastautil::DECKS,
+        canastautil::HAND_SIZE,
+        seed,
+    )
+}
+
+/// Creates a game dealt from `seed`. Release it with `canasta_game_free`.
+#[no_mangle]
+pub extern "C" fn canasta_game_new(seed: u64) -> *mut CanastaGame {
+    Box::into_raw(Box::new(CanastaGame { game: deal(seed) }))
+}
+
+/// Releases a game. Does nothing if `game` is null.
+///
+/// # Safety
+/// `game` must come from `canasta_game_new` and not have been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn canasta_game_free(game: *mut CanastaGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Deals a new game from `seed` in place of `game`.
+///
+/// # Safety
+/// `game` must be null or a live game.
+#[no_mangle]
+pub unsafe extern "C" fn canasta_game_reset(game: *mut CanastaGame, seed: u64) -> CanastaStatus {
+    match game.as_mut() {
+        Some(game) => {
+            game.game = deal(seed);
+            CanastaStatus::Ok
+        }
+        None => CanastaStatus::NullPointer,
+    }
+}
+
+/// The seat to play, or -1 if `game` is null.
+///
+/// # Safety
+/// `game` must be null or a live game.
+#[no_mangle]
+pub unsafe extern "C" fn canasta_current_player(game: *const CanastaGame) -> i32 {
+    match game.as_ref() {
+        Some(game) => game.game.turn.get() as i32,
+        None => -1,
+    }
+}
+
+/// Whether the game is over, false if `game` is null.
+///
+/// # Safety
+/// `game` must be null or a live game.
+#[no_mangle]
+pub unsafe extern "C" fn canasta_finished(game: *const CanastaGame) -> bool {
+    match game.as_ref() {
+        Some(game) => game.game.finished,
+        None => false,
+    }
+}
+
+/// Writes the encoding of the game seen by the seat to play, `CANASTA_STATE_SIZE` floats.
+///
+/// # Safety
+/// `game` must be null or a live game and `out` must be null or point to `len` floats.
+#[no_mangle]
+pub unsafe extern "C" fn canasta_observation(
+    game: *const CanastaGame,
+    out: *mut f32,
+    len: usize,
+) -> CanastaStatus {
+    let game = match (game.as_ref(), out.is_null()) {
+        (Some(game), false) => game,
+        _ => return CanastaStatus::NullPointer,
+    };
+    if len < STATE_SIZE {
+        return CanastaStatus::BufferTooSmall;
+    }
+    let encoded: [f32; STATE_SIZE] = GameState::<PLAYERS_PER_TEAM, TEAMS_COUNT> {
+        game: game.game.clone(),
+    }
+    .into();
+    slice::from_raw_parts_mut(out, STATE_SIZE).copy_from_slice(&encoded);
+    CanastaStatus::Ok
+}
+
+/// Writes the legality of every action of the seat to play, `CANASTA_ACTION_SIZE` booleans.
+///
+/// # Safety
+/// `game` must be null or a live game and `out` must be null or point to `len` booleans.
+#[no_mangle]
+pub unsafe extern "C" fn canasta_legal_mask(
+    game: *const CanastaGame,
+    out: *mut bool,
+    len: usize,
+) -> CanastaStatus {
+    let game = match (game.as_ref(), out.is_null()) {
+        (Some(game), false) => game,
+        _ => return CanastaStatus::NullPointer,
+    };
+    if len < ACTION_SIZE {
+        return CanastaStatus::BufferTooSmall;
+    }
+    slice::from_raw_parts_mut(out, ACTION_SIZE).copy_from_slice(&game.game.legal_mask());
+    CanastaStatus::Ok
+}
+
+/// Makes `action` for the seat to play.
+///
+/// # Safety
+/// `game` must be null or a live game.
+#[no_mangle]
+pub unsafe extern "C" fn canasta_apply_action(
+    game: *mut CanastaGame,
+    action: usize,
+) -> CanastaStatus {
+    let game = match game.as_mut() {
+        Some(game) => &mut game.game,
+        None => return CanastaStatus::NullPointer,
+    };
+    if action >= ACTION_SIZE {
+        return CanastaStatus::InvalidAction;
+    }
+    if game.finished {
+        return CanastaStatus::GameOver;
+    }
+    let play = Action::from(action).play;
+    if !game.check_legal(play) {
+        return CanastaStatus::IllegalAction;
+    }
+    game.execute_play(play);
+    CanastaStatus::Ok
+}
+
+/// Writes the score of every seat, `CANASTA_PLAYERS` values.
+///
+/// # Safety
+/// `game` must be null or a live game and `out` must be null or point to `len` values.
+#[no_mangle]
+pub unsafe extern "C" fn canasta_scores(
+    game: *const CanastaGame,
+    out: *mut i16,
+    len: usize,
+) -> CanastaStatus {
+    let game = match (game.as_ref(), out.is_null()) {
+        (Some(game), false) => game,
+        _ => return CanastaStatus::NullPointer,
+    };
+    let scores = game.game.get_scores();
+    if len < scores.len() {
+        return CanastaStatus::BufferTooSmall;
+    }
+    slice::from_raw_parts_mut(out, scores.len()).copy_from_slice(&scores);
+    CanastaStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn play_a_game_through_the_c_api() {
+        unsafe {
+            let game = canasta_game_new(3);
+            let mut observation = [0.0f32; CANASTA_STATE_SIZE];
+            let mut mask = [false; CANASTA_ACTION_SIZE];
+            let mut scores = [0i16; CANASTA_PLAYERS];
+
+            assert_eq!(
+                canasta_observation(game, observation.as_mut_ptr(), 10),
+                CanastaStatus::BufferTooSmall
+            );
+            assert_eq!(canasta_apply_action(game, 0), CanastaStatus::IllegalAction);
+            assert_eq!(
+                canasta_apply_action(game, CANASTA_ACTION_SIZE),
+                CanastaStatus::InvalidAction
+            );
+            while !canasta_finished(game) {
+                let status = canasta_observation(game, observation.as_mut_ptr(), observation.len());
+                assert_eq!(status, CanastaStatus::Ok);
+                assert_eq!(
+                    canasta_legal_mask(game, mask.as_mut_ptr(), mask.len()),
+                    CanastaStatus::Ok
+                );
+                let action = mask.iter().position(|legal| *legal).unwrap();
+                assert_eq!(canasta_apply_action(game, action), CanastaStatus::Ok);
+            }
+            assert_eq!(canasta_apply_action(game, 14), CanastaStatus::GameOver);
+            assert_eq!(
+                canasta_scores(game, scores.as_mut_ptr(), scores.len()),
+                CanastaStatus::Ok
+            );
+            assert_eq!(scores.to_vec(), (*game).game.get_scores());
+            canasta_game_free(game);
+
+            assert_eq!(canasta_current_player(ptr::null()), -1);
+            assert_eq!(
+                canasta_game_reset(ptr::null_mut(), 0),
+                CanastaStatus::NullPointer
+            );
+            canasta_game_free(ptr::null_mut());
+        }
+    }
+}
@@ -1,38 +1,97 @@
 // source: https://raw.githubusercontent.com/coreylowman/dfdx/main/examples/rl-dqn.rs
 
-use dfdx::{
-    nn,
-    optim::{Momentum, Sgd, SgdConfig},
-    prelude::*,
-};
+use dfdx::{optim::WeightDecay, prelude::*};
 
-use canasta_rl::mdp::{Agent, State};
-use canasta_rl::strategy::{explore::ExplorationStrategy, terminate::TerminationStrategy};
-use rand::Rng;
+use canasta_rl::mdp::State;
+use canasta_rl::strategy::explore::{EpsilonGreedy, ExplorationStrategy};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+pub mod memory;
+pub mod network;
+pub mod optim;
+
+use memory::{Prioritized, ReplayMemory, Transition};
+pub(crate) use network::with_network;
+use network::{disable_noise, resample_noise};
+pub use network::{NetworkInfo, QNetworkDevice, QNetworkModel};
+pub use optim::{DQNOptimizer, LrSchedule, OptimizerKind};
 
 const BATCH: usize = 64;
+// Probability of acting at random rather than greedily while training, unless another
+// exploration strategy is given
+const EXPLORATION: f64 = 0.1;
+// Added to the values of illegal next actions, so that the target never picks them
+const ILLEGAL_PENALTY: f32 = -1e9;
+
+/// How the target network, which gives the values of next states when training, follows the
+/// network being trained.
+#[derive(Clone, Debug)]
+pub enum TargetUpdate {
+    /// Copied from it every given number of gradient steps.
+    Periodic(usize),
+    /// Moved towards it by the given fraction `tau` after every gradient step.
+    Polyak(f32),
+}
 
-type QNetwork<const STATE_SIZE: usize, const ACTION_SIZE: usize, const INNER_SIZE: usize> = (
-    (Linear<STATE_SIZE, INNER_SIZE>, ReLU),
-    (Linear<INNER_SIZE, INNER_SIZE>, ReLU),
-    Linear<INNER_SIZE, ACTION_SIZE>,
-);
+/// Settings of a `DQNAgentTrainer`.
+#[derive(Clone, Debug)]
+pub struct DQNConfig {
+    /// The discount factor for future rewards.
+    pub gamma: f32,
+    /// The learning rate for the optimizer, before the warmup and the schedule.
+    pub learning_rate: f64,
+    pub optimizer: OptimizerKind,
+    pub weight_decay: Option<WeightDecay>,
+    /// Gradients are scaled down to this global norm when above it.
+    pub max_grad_norm: Option<f32>,
+    pub lr_schedule: LrSchedule,
+    /// How many gradient steps the learning rate takes to rise linearly to `learning_rate`.
+    pub warmup_steps: usize,
+    /// How many transitions the replay memory holds.
+    pub memory_capacity: usize,
+    /// How many transitions are observed between two gradient steps, each on a batch sampled from
+    /// the replay memory.
+    pub train_every: usize,
+    /// How the target network follows the network being trained.
+    pub target_update: TargetUpdate,
+    /// Double DQN: the network being trained picks the best legal next action and the target
+    /// network values it, rather than the target network doing both.
+    pub double: bool,
+    /// Prioritized replay, or `None` to sample the replay memory uniformly.
+    pub prioritized: Option<Prioritized>,
+    /// Seed of the sampling from the replay memory.
+    pub seed: u64,
+}
 
-pub type QNetworkDevice<
-    const STATE_SIZE: usize,
-    const ACTION_SIZE: usize,
-    const INNER_SIZE: usize,
-> = (
-    (nn::modules::Linear<STATE_SIZE, INNER_SIZE, f32, Cuda>, ReLU),
-    (nn::modules::Linear<INNER_SIZE, INNER_SIZE, f32, Cuda>, ReLU),
-    nn::modules::Linear<INNER_SIZE, ACTION_SIZE, f32, Cuda>,
-);
-
-/// An `DQNAgentTrainer` can be trained for using a certain [Agent](mdp/trait.Agent.html). After
-/// training, the `DQNAgentTrainer` contains learned knowledge about the process, and can be queried
-/// for this. For example, you can ask the `DQNAgentTrainer` the expected values of all possible
+impl Default for DQNConfig {
+    fn default() -> Self {
+        DQNConfig {
+            gamma: 0.99,
+            learning_rate: 1e-3,
+            optimizer: Default::default(),
+            weight_decay: None,
+            max_grad_norm: None,
+            lr_schedule: LrSchedule::Constant,
+            warmup_steps: 0,
+            memory_capacity: 10_000,
+            train_every: 4,
+            target_update: TargetUpdate::Periodic(1_000),
+            double: false,
+            prioritized: None,
+            seed: 0,
+        }
+    }
+}
+
+/// An `DQNAgentTrainer` learns from the transitions it is given with `observe`, replaying them from
+/// its memory, and chooses actions with `act_batch`. After training, the `DQNAgentTrainer`
+/// contains learned knowledge about the process, and can be queried for this. For example, you can ask the `DQNAgentTrainer` the expected values of all possible
 /// actions in a given state.
 ///
+/// The network `M` is `QNetworkDevice` unless another one, such as `DuelingQNetworkDevice`, is
+/// chosen.
+///
 /// The code is partially taken from https://github.com/coreylowman/dfdx/blob/main/examples/rl-dqn.rs.
 ///
 pub struct DQNAgentTrainer<
@@ -40,26 +99,38 @@ pub struct DQNAgentTrainer<
     const STATE_SIZE: usize,
     const ACTION_SIZE: usize,
     const INNER_SIZE: usize,
+    M = QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>,
 > where
     S: State + Into<[f32; STATE_SIZE]>,
     S::A: Into<[f32; ACTION_SIZE]>,
     S::A: From<[f32; ACTION_SIZE]>,
+    M: QNetworkModel<STATE_SIZE, ACTION_SIZE>,
 {
     // values future rewards
     gamma: f32,
-    q_network: QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>,
-    target_q_net: QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>,
-    sgd: Sgd<QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>, f32, Cuda>,
+    q_network: M,
+    target_q_net: M,
+    target_update: TargetUpdate,
+    double: bool,
+    // Gradient steps taken so far
+    gradient_steps: usize,
+    optimizer: DQNOptimizer<M>,
     dev: Cuda,
-    phantom: std::marker::PhantomData<S>,
+    memory: ReplayMemory<STATE_SIZE, ACTION_SIZE>,
+    train_every: usize,
+    // Transitions observed so far
+    steps: usize,
+    rng: StdRng,
+    exploration: Box<dyn ExplorationStrategy<S>>,
 }
 
-impl<S, const STATE_SIZE: usize, const ACTION_SIZE: usize, const INNER_SIZE: usize>
-    DQNAgentTrainer<S, STATE_SIZE, ACTION_SIZE, INNER_SIZE>
+impl<S, const STATE_SIZE: usize, const ACTION_SIZE: usize, const INNER_SIZE: usize, M>
+    DQNAgentTrainer<S, STATE_SIZE, ACTION_SIZE, INNER_SIZE, M>
 where
     S: State + Into<[f32; STATE_SIZE]>,
     S::A: Into<[f32; ACTION_SIZE]>,
     S::A: From<[f32; ACTION_SIZE]>,
+    M: QNetworkModel<STATE_SIZE, ACTION_SIZE>,
 {
     /// Creates a new `DQNAgentTrainer` with the given parameters.
     ///
@@ -72,88 +143,132 @@ where
     ///
     /// A new `DQNAgentTrainer` with the given parameters.
     ///
-    pub fn new(
-        gamma: f32,
-        learning_rate: f64,
-    ) -> DQNAgentTrainer<S, STATE_SIZE, ACTION_SIZE, INNER_SIZE> {
+    pub fn new(gamma: f32, learning_rate: f64) -> Self {
+        Self::with_config(DQNConfig {
+            gamma,
+            learning_rate,
+            ..Default::default()
+        })
+    }
+
+    /// Creates a new `DQNAgentTrainer` with the given settings.
+    pub fn with_config(config: DQNConfig) -> Self {
         let dev: Cuda = Default::default();
 
         // initialize model
-        let q_net = dev.build_module::<QNetwork<STATE_SIZE, ACTION_SIZE, INNER_SIZE>, f32>();
+        let q_net = M::build(&dev);
         let target_q_net = q_net.clone();
 
         // initialize optimizer
-        let sgd = Sgd::new(
-            &q_net,
-            SgdConfig {
-                lr: learning_rate,
-                momentum: Some(Momentum::Nesterov(0.9)),
-                weight_decay: None,
-            },
-        );
+        let optimizer = DQNOptimizer::new(&q_net, &config);
 
         DQNAgentTrainer {
-            gamma,
+            gamma: config.gamma,
             q_network: q_net,
             target_q_net,
-            sgd,
+            target_update: config.target_update,
+            double: config.double,
+            gradient_steps: 0,
+            optimizer,
             dev,
-            phantom: std::marker::PhantomData,
+            memory: match config.prioritized {
+                Some(settings) => ReplayMemory::prioritized(config.memory_capacity, settings),
+                None => ReplayMemory::new(config.memory_capacity),
+            },
+            train_every: config.train_every,
+            steps: 0,
+            rng: StdRng::seed_from_u64(config.seed),
+            exploration: Box::new(EpsilonGreedy::new(EXPLORATION)),
         }
     }
 
+    /// Replaces the exploration strategy, epsilon-greedy with a constant epsilon of 0.1 by default.
+    /// It picks every action of `act_batch` and is told of the episodes ending in `observe`.
+    pub fn with_exploration(mut self, strategy: impl ExplorationStrategy<S> + 'static) -> Self {
+        self.exploration = Box::new(strategy);
+        self
+    }
+
+    /// Silences the noise of the `NoisyLinear` layers of the network, so that states are evaluated
+    /// with their mean weights, until the next `observe` samples new noise.
+    pub fn disable_noise(&mut self) {
+        disable_noise(&mut self.q_network);
+    }
+
     /// Fetches the learned value for the given `Action` in the given `State`, or `None` if no
     /// value was learned.
     pub fn expected_value(&self, state: &S) -> [f32; ACTION_SIZE] {
         let state_: [f32; STATE_SIZE] = (state.clone()).into();
         let states: Tensor<Rank1<STATE_SIZE>, f32, _> =
             self.dev.tensor(state_).normalize::<Axis<0>>(0.001);
-        let actions = self.target_q_net.forward(states).nans_to(0f32);
+        let actions = self.q_network.forward(states).nans_to(0f32);
+        actions.array()
+    }
+
+    /// Like `expected_value` for `N` states at once, evaluated in a single forward pass.
+    pub fn expected_values<const N: usize>(&self, states: &[S; N]) -> [[f32; ACTION_SIZE]; N]
+    where
+        M: Module<
+            Tensor<Rank2<N, STATE_SIZE>, f32, Cuda>,
+            Output = Tensor<Rank2<N, ACTION_SIZE>, f32, Cuda>,
+        >,
+    {
+        let states_: [[f32; STATE_SIZE]; N] = states.clone().map(|state| state.into());
+        let states: Tensor<Rank2<N, STATE_SIZE>, f32, _> =
+            self.dev.tensor(states_).normalize::<Axis<1>>(0.001);
+        let actions = self.q_network.forward(states).nans_to(0f32);
         actions.array()
     }
 
     /// Returns a clone of the entire learned state to be saved or used elsewhere.
-    pub fn export_learned_values(&self) -> QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE> {
+    pub fn export_learned_values(&self) -> M {
         self.learned_values().clone()
     }
 
     // Returns a reference to the learned state.
-    pub fn learned_values(&self) -> &QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE> {
+    pub fn learned_values(&self) -> &M {
         &self.q_network
     }
 
     /// Imports a model, completely replacing any learned progress
-    pub fn import_model(&mut self, model: QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>) {
+    pub fn import_model(&mut self, model: M) {
         self.q_network.clone_from(&model);
         self.target_q_net.clone_from(&self.q_network);
     }
 
+    /// Saves the learned model to a `.safetensors` file.
+    pub fn save_model(&self, path: &str) -> Result<(), String> {
+        self.q_network
+            .save_safetensors(path)
+            .map_err(|e| format!("Could not save model to {}: {:?}", path, e))
+    }
+
+    /// Loads a model saved with `save_model`, completely replacing any learned progress
+    pub fn load_model(&mut self, path: &str) -> Result<(), String> {
+        self.q_network
+            .load_safetensors(path)
+            .map_err(|e| format!("Could not load model from {}: {:?}", path, e))?;
+        self.target_q_net.clone_from(&self.q_network);
+        Ok(())
+    }
+
     /// Returns the best action for the given `State`, or `None` if no values were learned.
     pub fn best_action(&self, state: &S) -> Option<S::A> {
-        let mut target = self.expected_value(state);
-        //check if each action is legal and if not set it to -inf
-        let mut legal_target = [0f32; ACTION_SIZE];
-        for (i, v) in target.iter_mut().enumerate() {
-            if !state.check_legal(i) {
-                legal_target[i] = -std::f32::INFINITY;
-            } else {
-                legal_target[i] = *v;
-            }
-        }
-        Some(legal_target.into())
+        Some(best_legal(state, self.expected_value(state)))
     }
 
-    #[allow(clippy::boxed_local)]
+    #[allow(clippy::boxed_local, clippy::too_many_arguments)]
     pub fn train_dqn(
         &mut self,
         states: Box<[[f32; STATE_SIZE]; BATCH]>,
         actions: [[f32; ACTION_SIZE]; BATCH],
         next_states: Box<[[f32; STATE_SIZE]; BATCH]>,
+        next_legal: [[bool; ACTION_SIZE]; BATCH],
         rewards: [f32; BATCH],
         dones: [bool; BATCH],
-    ) {
-        self.target_q_net.clone_from(&self.q_network);
-        let mut grads = self.q_network.alloc_grads();
+        weights: [f32; BATCH],
+    ) -> [f32; BATCH] {
+        let grads = self.q_network.alloc_grads();
 
         let dones: Tensor<Rank1<BATCH>, f32, _> =
             self.dev.tensor(dones.map(|d| if d { 1f32 } else { 0f32 }));
@@ -181,102 +296,188 @@ where
             self.dev.tensor(*next_states).normalize::<Axis<1>>(0.001);
 
         // Compute the estimated Q-value for the action
-        for _step in 0..20 {
-            let q_values = self.q_network.forward(states.trace(grads));
-
-            let action_qs = q_values.select(actions.clone());
+        let q_values = self.q_network.forward_mut(states.trace(grads));
 
-            // targ_q = R + discount * max(Q(S'))
-            // curr_q = Q(S)[A]
-            // loss = huber(curr_q, targ_q, 1)
-            let next_q_values = self.target_q_net.forward(next_states.clone());
-            let max_next_q = next_q_values.max::<Rank1<BATCH>, _>();
-            let target_q = (max_next_q * (-dones.clone() + 1.0)) * self.gamma + rewards.clone();
+        let action_qs = q_values.select(actions);
 
-            let loss = huber_loss(action_qs, target_q, 1.0);
-
-            grads = loss.backward();
+        // targ_q = R + discount * max(Q(S')) over the legal actions of S'
+        // curr_q = Q(S)[A]
+        // loss = mean(weights * huber(curr_q, targ_q, 1))
+        let penalty: Tensor<Rank2<BATCH, ACTION_SIZE>, f32, _> = self.dev.tensor(
+            next_legal.map(|legal| legal.map(|legal| if legal { 0.0 } else { ILLEGAL_PENALTY })),
+        );
+        let next_q_values = self.target_q_net.forward(next_states.clone()) + penalty.clone();
+        let next_q = if self.double {
+            let online_q_values = (self.q_network.forward(next_states) + penalty).array();
+            let next_actions = self
+                .dev
+                .tensor(online_q_values.map(|values| argmax(&values)));
+            next_q_values.select(next_actions)
+        } else {
+            next_q_values.max::<Rank1<BATCH>, _>()
+        };
+        let target_q = (next_q * (-dones + 1.0)) * self.gamma + rewards;
+
+        let curr_q = action_qs.array();
+        let targ_q = target_q.array();
+        let weights = self.dev.tensor(weights);
+        let loss = (action_qs.huber_error(target_q, 1.0) * weights).mean();
+
+        let grads = loss.backward();
+
+        // update weights with optimizer
+        self.optimizer
+            .update(&mut self.q_network, grads, self.gradient_steps);
+        self.update_target();
+        std::array::from_fn(|i| targ_q[i] - curr_q[i])
+    }
 
-            // update weights with optimizer
-            self.sgd
-                .update(&mut self.q_network, &grads)
-                .expect("Unused params");
-            self.q_network.zero_grads(&mut grads);
-        }
-        self.target_q_net.clone_from(&self.q_network);
+    /// Picks the actions to take in the states of `N` where `acting` is set with the exploration
+    /// strategy, their values evaluated in a single forward pass. The other states are skipped,
+    /// so the strategy only sees the decisions actually made.
+    pub fn act_batch<const N: usize, R: RngCore>(
+        &self,
+        states: &[S; N],
+        acting: &[bool; N],
+        rng: &mut R,
+    ) -> [Option<S::A>; N]
+    where
+        M: Module<
+            Tensor<Rank2<N, STATE_SIZE>, f32, Cuda>,
+            Output = Tensor<Rank2<N, ACTION_SIZE>, f32, Cuda>,
+        >,
+    {
+        let values = self.expected_values(states);
+        std::array::from_fn(|i| {
+            if !acting[i] {
+                return None;
+            }
+            let values = values[i].map(f64::from);
+            let legal = states[i].legal_mask();
+            let index = self
+                .exploration
+                .pick_action(&states[i], &values, &legal, rng);
+            let mut action = [0.0; ACTION_SIZE];
+            action[index] = 1.0;
+            Some(action.into())
+        })
     }
 
-    /// Trains this [DQNAgentTrainer] using the given [ExplorationStrategy] and
-    /// [Agent] until the [TerminationStrategy] decides to stop.
-    pub fn train(
-        &mut self,
-        agent: &mut dyn Agent<S>,
-        termination_strategy: &mut dyn TerminationStrategy<S>,
-        exploration_strategy: &dyn ExplorationStrategy<S>,
-    ) {
-        loop {
-            // Initialize batch
-            let mut states: Box<[[f32; STATE_SIZE]; BATCH]> = {
-                let b = vec![0.0; STATE_SIZE].into_boxed_slice();
-                let big = unsafe { Box::from_raw(Box::into_raw(b) as *mut [f32; STATE_SIZE]) };
-
-                let b = vec![*big; BATCH].into_boxed_slice();
-                unsafe { Box::from_raw(Box::into_raw(b) as *mut [[f32; STATE_SIZE]; BATCH]) }
-            };
-            let mut actions: [[f32; ACTION_SIZE]; BATCH] = [[0.0; ACTION_SIZE]; BATCH];
-            let mut next_states: Box<[[f32; STATE_SIZE]; BATCH]> = {
-                let b = vec![0.0; STATE_SIZE].into_boxed_slice();
-                let big = unsafe { Box::from_raw(Box::into_raw(b) as *mut [f32; STATE_SIZE]) };
-
-                let b = vec![*big; BATCH].into_boxed_slice();
-                unsafe { Box::from_raw(Box::into_raw(b) as *mut [[f32; STATE_SIZE]; BATCH]) }
-            };
-            let mut rewards: [f32; BATCH] = [0.0; BATCH];
-            let mut dones = [false; BATCH];
-
-            let mut s_t_next = agent.current_state();
-            
-            for i in 0..BATCH {
-                let s_t = agent.current_state().clone();
-                let mut action : S::A = exploration_strategy.pick_action(agent);
-                while !s_t.check_legal_action(action.clone()) { action = exploration_strategy.pick_action(agent); }
-                if rand::thread_rng().gen::<f32>() > 0.1 {
-                    action = self.best_action(&s_t).unwrap();
+    // Brings the target network closer to the network being trained after a gradient step
+    fn update_target(&mut self) {
+        self.gradient_steps += 1;
+        match self.target_update {
+            TargetUpdate::Periodic(steps) => {
+                if self.gradient_steps.is_multiple_of(steps) {
+                    self.target_q_net.clone_from(&self.q_network);
                 }
-                agent.take_action(&action);
-                // current action value
-
-                s_t_next = agent.current_state();
-                let r_t_next = s_t_next.reward();
+            }
+            TargetUpdate::Polyak(tau) => self.target_q_net.ema(&self.q_network, 1.0 - tau),
+        }
+    }
 
-                states[i] = s_t.into();
-                actions[i] = action.into();
-                next_states[i] = (s_t_next).clone().into();
-                rewards[i] = r_t_next as f32;
+    /// Learns from a transition: the agent took `action` in `state`, got `reward` and then saw
+    /// `next_state`. The transition is kept in the replay memory, and every `train_every`
+    /// transitions the network is trained on a batch sampled from it. The noise of noisy layers
+    /// is resampled at every transition.
+    pub fn observe(&mut self, state: S, action: S::A, reward: f64, next_state: S, done: bool) {
+        let next_legal = next_state.legal_mask();
+        self.memory.push(Transition {
+            state: state.into(),
+            action: action.into(),
+            next_legal: std::array::from_fn(|i| next_legal[i]),
+            next_state: next_state.into(),
+            reward: reward as f32,
+            done,
+        });
+        if done {
+            self.exploration.end_episode();
+        }
+        resample_noise(&mut self.q_network);
+        self.steps += 1;
+        if self.steps.is_multiple_of(self.train_every) && self.memory.len() >= BATCH {
+            self.learn_batch();
+        }
+    }
 
-                if termination_strategy.should_stop(&s_t_next) {
-                    dones[i] = true;
-                    break;
-                }
-            }
+    // Trains on a batch sampled from the replay memory, updating the priorities of the batch
+    fn learn_batch(&mut self) {
+        // The target network evaluates next states with noise of its own
+        resample_noise(&mut self.target_q_net);
+        let mut states = zeroed_batch::<STATE_SIZE>();
+        let mut actions = [[0.0; ACTION_SIZE]; BATCH];
+        let mut next_states = zeroed_batch::<STATE_SIZE>();
+        let mut next_legal = [[false; ACTION_SIZE]; BATCH];
+        let mut rewards = [0.0; BATCH];
+        let mut dones = [false; BATCH];
+        let mut weights = [1.0; BATCH];
+        let sample = self.memory.sample(BATCH, &mut self.rng);
+        weights.copy_from_slice(&sample.weights);
+        let indices = sample.indices;
+        for (i, transition) in sample.transitions.into_iter().enumerate() {
+            states[i] = transition.state;
+            actions[i] = transition.action;
+            next_states[i] = transition.next_state;
+            next_legal[i] = transition.next_legal;
+            rewards[i] = transition.reward;
+            dones[i] = transition.done;
+        }
+        let td_errors = self.train_dqn(
+            states,
+            actions,
+            next_states,
+            next_legal,
+            rewards,
+            dones,
+            weights,
+        );
+        self.memory.update_priorities(&indices, &td_errors);
+    }
+}
 
-            // train the network
-            self.train_dqn(states, actions, next_states, rewards, dones);
+// The index of the highest value
+fn argmax(values: &[f32]) -> usize {
+    let mut best = 0;
+    for (i, v) in values.iter().enumerate() {
+        if *v > values[best] {
+            best = i;
+        }
+    }
+    best
+}
 
-            // terminate if the agent is done
-            if termination_strategy.should_stop(&s_t_next) {
-                break;
-            }
+// The legal action of `state` with the highest value
+fn best_legal<S, const ACTION_SIZE: usize>(state: &S, mut values: [f32; ACTION_SIZE]) -> S::A
+where
+    S: State,
+    S::A: From<[f32; ACTION_SIZE]>,
+{
+    //set the value of every illegal action to -inf
+    let legal = state.legal_mask();
+    for (i, v) in values.iter_mut().enumerate() {
+        if !legal[i] {
+            *v = -f32::INFINITY;
         }
     }
+    values.into()
+}
+
+// A batch of `BATCH` zeroed rows, allocated on the heap directly as it may not fit on the stack
+fn zeroed_batch<const SIZE: usize>() -> Box<[[f32; SIZE]; BATCH]> {
+    let b = vec![0.0; SIZE].into_boxed_slice();
+    let big = unsafe { Box::from_raw(Box::into_raw(b) as *mut [f32; SIZE]) };
+
+    let b = vec![*big; BATCH].into_boxed_slice();
+    unsafe { Box::from_raw(Box::into_raw(b) as *mut [[f32; SIZE]; BATCH]) }
 }
 
-impl<S, const STATE_SIZE: usize, const ACTION_SIZE: usize, const INNER_SIZE: usize> Default
-    for DQNAgentTrainer<S, STATE_SIZE, ACTION_SIZE, INNER_SIZE>
+impl<S, const STATE_SIZE: usize, const ACTION_SIZE: usize, const INNER_SIZE: usize, M> Default
+    for DQNAgentTrainer<S, STATE_SIZE, ACTION_SIZE, INNER_SIZE, M>
 where
     S: State + Into<[f32; STATE_SIZE]>,
     S::A: Into<[f32; ACTION_SIZE]>,
     S::A: From<[f32; ACTION_SIZE]>,
+    M: QNetworkModel<STATE_SIZE, ACTION_SIZE>,
 {
     fn default() -> Self {
         Self::new(0.99, 1e-3)
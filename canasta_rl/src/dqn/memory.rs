@@ -0,0 +1,286 @@
+//! Replay memory: the last transitions of an agent, sampled to train it.
+//!
+//! Sampling is either uniform or prioritized (Schaul et al., 2016): transitions are replayed in
+//! proportion to their last TD error, and importance-sampling weights correct for the bias.
+
+use rand::Rng;
+
+/// A step of the agent, encoded for the network.
+#[derive(Clone)]
+pub struct Transition<const STATE_SIZE: usize, const ACTION_SIZE: usize> {
+    pub state: [f32; STATE_SIZE],
+    pub action: [f32; ACTION_SIZE],
+    pub next_state: [f32; STATE_SIZE],
+    /// Legality of the actions in the next state.
+    pub next_legal: [bool; ACTION_SIZE],
+    pub reward: f32,
+    pub done: bool,
+}
+
+/// Settings of prioritized replay.
+#[derive(Clone, Debug)]
+pub struct Prioritized {
+    /// How much priorities count: 0 samples uniformly, 1 in proportion to the TD errors.
+    pub alpha: f32,
+    /// The importance-sampling exponent at the start of training, annealed to 1.
+    pub beta: f32,
+    /// How many samples it takes for the importance-sampling exponent to reach 1.
+    pub beta_steps: usize,
+    /// Added to the TD errors, so that every transition may be replayed.
+    pub epsilon: f32,
+}
+
+impl Default for Prioritized {
+    fn default() -> Self {
+        Prioritized {
+            alpha: 0.6,
+            beta: 0.4,
+            beta_steps: 100_000,
+            epsilon: 1e-3,
+        }
+    }
+}
+
+// A binary tree whose leaves are the priorities and every other node the sum of its children,
+// stored as a heap: the root is at 1, the children of n at 2n and 2n + 1, the leaves from
+// `capacity` on
+struct SumTree {
+    nodes: Vec<f64>,
+    capacity: usize,
+}
+
+impl SumTree {
+    fn new(capacity: usize) -> SumTree {
+        SumTree {
+            nodes: vec![0.0; 2 * capacity],
+            capacity,
+        }
+    }
+
+    fn total(&self) -> f64 {
+        self.nodes[1]
+    }
+
+    fn get(&self, leaf: usize) -> f64 {
+        self.nodes[leaf + self.capacity]
+    }
+
+    fn set(&mut self, leaf: usize, priority: f64) {
+        let mut node = leaf + self.capacity;
+        self.nodes[node] = priority;
+        while node > 1 {
+            node /= 2;
+            self.nodes[node] = self.nodes[2 * node] + self.nodes[2 * node + 1];
+        }
+    }
+
+    // The leaf where the running sum of priorities goes past `mass`
+    fn find(&self, mut mass: f64) -> usize {
+        let mut node = 1;
+        while node < self.capacity {
+            if mass < self.nodes[2 * node] {
+                node *= 2;
+            } else {
+                mass -= self.nodes[2 * node];
+                node = 2 * node + 1;
+            }
+        }
+        node - self.capacity
+    }
+}
+
+struct Priorities {
+    settings: Prioritized,
+    tree: SumTree,
+    // Given to new transitions, so that they are replayed at least once
+    max_priority: f32,
+    // Samples taken so far, for annealing beta
+    samples: usize,
+}
+
+/// Transitions sampled from a `ReplayMemory`.
+pub struct Sample<'a, const STATE_SIZE: usize, const ACTION_SIZE: usize> {
+    /// Where the transitions are in the memory, to update their priorities.
+    pub indices: Vec<usize>,
+    pub transitions: Vec<&'a Transition<STATE_SIZE, ACTION_SIZE>>,
+    /// Importance-sampling weights of the transitions, at most 1. All 1 for uniform sampling.
+    pub weights: Vec<f32>,
+}
+
+/// A ring buffer holding up to `capacity` transitions, the oldest being overwritten once it is
+/// full.
+pub struct ReplayMemory<const STATE_SIZE: usize, const ACTION_SIZE: usize> {
+    transitions: Vec<Transition<STATE_SIZE, ACTION_SIZE>>,
+    capacity: usize,
+    // Where the next transition goes once the memory is full
+    next: usize,
+    priorities: Option<Priorities>,
+}
+
+impl<const STATE_SIZE: usize, const ACTION_SIZE: usize> ReplayMemory<STATE_SIZE, ACTION_SIZE> {
+    /// Creates an empty memory sampled uniformly. Space is only taken as transitions are added.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "A replay memory needs room for a transition");
+        ReplayMemory {
+            transitions: Vec::new(),
+            capacity,
+            next: 0,
+            priorities: None,
+        }
+    }
+
+    /// Creates an empty memory with prioritized sampling.
+    pub fn prioritized(capacity: usize, settings: Prioritized) -> Self {
+        ReplayMemory {
+            priorities: Some(Priorities {
+                settings,
+                tree: SumTree::new(capacity),
+                max_priority: 1.0,
+                samples: 0,
+            }),
+            ..ReplayMemory::new(capacity)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Adds `transition`, replacing the oldest one if the memory is full. It gets the highest
+    /// priority seen so far.
+    pub fn push(&mut self, transition: Transition<STATE_SIZE, ACTION_SIZE>) {
+        let index = if self.transitions.len() < self.capacity {
+            self.transitions.push(transition);
+            self.transitions.len() - 1
+        } else {
+            let index = self.next;
+            self.transitions[index] = transition;
+            self.next = (self.next + 1) % self.capacity;
+            index
+        };
+        if let Some(priorities) = &mut self.priorities {
+            let priority = priorities.max_priority.powf(priorities.settings.alpha);
+            priorities.tree.set(index, priority as f64);
+        }
+    }
+
+    /// Picks `count` transitions with replacement, uniformly or by priority. The memory must not
+    /// be empty.
+    pub fn sample<R: Rng>(
+        &mut self,
+        count: usize,
+        rng: &mut R,
+    ) -> Sample<'_, STATE_SIZE, ACTION_SIZE> {
+        let len = self.transitions.len();
+        let (indices, weights) = match &mut self.priorities {
+            None => (
+                (0..count).map(|_| rng.gen_range(0..len)).collect(),
+                vec![1.0; count],
+            ),
+            Some(priorities) => {
+                let settings = &priorities.settings;
+                let progress = priorities.samples as f32 / settings.beta_steps.max(1) as f32;
+                let beta = settings.beta + (1.0 - settings.beta) * progress.min(1.0);
+                priorities.samples += 1;
+
+                // One transition from each of `count` slices of equal total priority
+                let total = priorities.tree.total();
+                let slice = total / count as f64;
+                let indices: Vec<usize> = (0..count)
+                    .map(|i| {
+                        let mass = slice * (i as f64 + rng.gen::<f64>());
+                        priorities.tree.find(mass.min(total)).min(len - 1)
+                    })
+                    .collect();
+                let weights: Vec<f32> = indices
+                    .iter()
+                    .map(|i| {
+                        let probability = priorities.tree.get(*i) / total;
+                        (len as f64 * probability).powf(-beta as f64) as f32
+                    })
+                    .collect();
+                // Scaled by the largest weight of the sample, so that they only ever shrink updates
+                let max_weight = weights.iter().cloned().fold(f32::MIN_POSITIVE, f32::max);
+                let weights = weights.iter().map(|w| w / max_weight).collect();
+                (indices, weights)
+            }
+        };
+        Sample {
+            transitions: indices.iter().map(|i| &self.transitions[*i]).collect(),
+            indices,
+            weights,
+        }
+    }
+
+    /// Sets the priorities of the transitions at `indices` from their new TD errors. Does nothing
+    /// if sampling is uniform.
+    pub fn update_priorities(&mut self, indices: &[usize], td_errors: &[f32]) {
+        if let Some(priorities) = &mut self.priorities {
+            for (index, error) in indices.iter().zip(td_errors) {
+                let priority = error.abs() + priorities.settings.epsilon;
+                priorities.max_priority = priorities.max_priority.max(priority);
+                let priority = priority.powf(priorities.settings.alpha);
+                priorities.tree.set(*index, priority as f64);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn transition(reward: f32) -> Transition<2, 1> {
+        Transition {
+            state: [0.0; 2],
+            action: [1.0],
+            next_state: [0.0; 2],
+            next_legal: [true],
+            reward,
+            done: false,
+        }
+    }
+
+    #[test]
+    fn oldest_transitions_are_replaced() {
+        let mut memory = ReplayMemory::new(3);
+        for reward in 0..5 {
+            memory.push(transition(reward as f32));
+        }
+        assert_eq!(memory.len(), 3);
+        let mut rewards: Vec<f32> = memory
+            .sample(100, &mut StdRng::seed_from_u64(0))
+            .transitions
+            .iter()
+            .map(|t| t.reward)
+            .collect();
+        rewards.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        rewards.dedup();
+        assert_eq!(rewards, vec![2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn sampling_follows_priorities() {
+        let settings = Prioritized {
+            alpha: 1.0,
+            beta: 1.0,
+            ..Default::default()
+        };
+        let mut memory = ReplayMemory::prioritized(5, settings);
+        for reward in 0..5 {
+            memory.push(transition(reward as f32));
+        }
+        // The last transition is 10 times as likely as each of the others
+        memory.update_priorities(&[0, 1, 2, 3, 4], &[1.0, 1.0, 1.0, 1.0, 10.0]);
+
+        let sample = memory.sample(1400, &mut StdRng::seed_from_u64(0));
+        let last = sample.indices.iter().filter(|i| **i == 4).count();
+        assert!((950..1050).contains(&last), "{} of 1400", last);
+        for (index, weight) in sample.indices.iter().zip(&sample.weights) {
+            let expected = if *index == 4 { 0.1 } else { 1.0 };
+            assert!((weight - expected).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,604 @@
+//! Networks a `DQNAgentTrainer` can train, giving the value of every action in a state.
+//!
+//! A network is a tuple of hidden layers followed by a head giving the values of the actions:
+//! `Dense`, `NormDense`, `DropoutDense` and `NoisyDense` layers of any width and activation, then
+//! a `Linear` layer, a `NoisyLinear` layer or a `DuelingHead`. The architectures below cover the
+//! usual choices, and any other tuple of layers, such as `(Dense<STATE_SIZE, 256, Tanh>,
+//! Dense<256, 64, Tanh>, Linear<64, ACTION_SIZE, f32, Cuda>)`, can be trained as well.
+
+use dfdx::{
+    nn::{
+        modules::{DropoutOneIn, LayerNorm1D, Linear, ReLU},
+        BuildModule, Module, ModuleMut, ModuleVisitor, NonMutableModule, RecursiveWalker,
+        TensorCollection, TensorOptions, TensorVisitor, ViewTensorMut, ViewTensorName,
+    },
+    prelude::*,
+};
+use rand::distributions::Uniform;
+use serde::{Deserialize, Serialize};
+
+use super::BATCH;
+
+/// A hidden layer of `OUT` units fed by `IN` inputs, with the activation `Act` (`ReLU`, `Tanh`,
+/// `GeLU`, ...).
+pub type Dense<const IN: usize, const OUT: usize, Act = ReLU> = (Linear<IN, OUT, f32, Cuda>, Act);
+
+/// A `Dense` layer normalized before its activation.
+pub type NormDense<const IN: usize, const OUT: usize, Act = ReLU> =
+    (Linear<IN, OUT, f32, Cuda>, LayerNorm1D<OUT, f32, Cuda>, Act);
+
+/// A `Dense` layer dropping each of its outputs with probability `1 / N` while training. Nothing
+/// is dropped when evaluating states.
+pub type DropoutDense<const IN: usize, const OUT: usize, const N: usize, Act = ReLU> =
+    (Linear<IN, OUT, f32, Cuda>, Act, DropoutOneIn<N>);
+
+/// A `Dense` layer whose weights are noisy, see `NoisyLinear`.
+pub type NoisyDense<const IN: usize, const OUT: usize, Act = ReLU> =
+    (NoisyLinear<IN, OUT, f32, Cuda>, Act);
+
+/// The plain network: two hidden layers, then the value of every action.
+pub type QNetworkDevice<
+    const STATE_SIZE: usize,
+    const ACTION_SIZE: usize,
+    const INNER_SIZE: usize,
+    Act = ReLU,
+> = (
+    Dense<STATE_SIZE, INNER_SIZE, Act>,
+    Dense<INNER_SIZE, INNER_SIZE, Act>,
+    Linear<INNER_SIZE, ACTION_SIZE, f32, Cuda>,
+);
+
+/// `QNetworkDevice` with a third hidden layer.
+pub type DeepQNetworkDevice<
+    const STATE_SIZE: usize,
+    const ACTION_SIZE: usize,
+    const INNER_SIZE: usize,
+    Act = ReLU,
+> = (
+    Dense<STATE_SIZE, INNER_SIZE, Act>,
+    Dense<INNER_SIZE, INNER_SIZE, Act>,
+    Dense<INNER_SIZE, INNER_SIZE, Act>,
+    Linear<INNER_SIZE, ACTION_SIZE, f32, Cuda>,
+);
+
+/// `QNetworkDevice` with layer normalization in both hidden layers.
+pub type NormQNetworkDevice<
+    const STATE_SIZE: usize,
+    const ACTION_SIZE: usize,
+    const INNER_SIZE: usize,
+    Act = ReLU,
+> = (
+    NormDense<STATE_SIZE, INNER_SIZE, Act>,
+    NormDense<INNER_SIZE, INNER_SIZE, Act>,
+    Linear<INNER_SIZE, ACTION_SIZE, f32, Cuda>,
+);
+
+/// `QNetworkDevice` with dropout of `1 / N` after both hidden layers. Its parameters are those of
+/// `QNetworkDevice`, so a saved model loads as either.
+pub type DropoutQNetworkDevice<
+    const STATE_SIZE: usize,
+    const ACTION_SIZE: usize,
+    const INNER_SIZE: usize,
+    const N: usize,
+    Act = ReLU,
+> = (
+    DropoutDense<STATE_SIZE, INNER_SIZE, N, Act>,
+    DropoutDense<INNER_SIZE, INNER_SIZE, N, Act>,
+    Linear<INNER_SIZE, ACTION_SIZE, f32, Cuda>,
+);
+
+/// The dueling network (Wang et al., 2016): the hidden layers of `QNetworkDevice`, then separate
+/// estimates of the value of the state and of the advantage of every action.
+pub type DuelingQNetworkDevice<
+    const STATE_SIZE: usize,
+    const ACTION_SIZE: usize,
+    const INNER_SIZE: usize,
+    Act = ReLU,
+> = (
+    Dense<STATE_SIZE, INNER_SIZE, Act>,
+    Dense<INNER_SIZE, INNER_SIZE, Act>,
+    DuelingHead<INNER_SIZE, ACTION_SIZE, f32, Cuda>,
+);
+
+/// The noisy network (Fortunato et al., 2018): `QNetworkDevice` with noisy weights in its second
+/// hidden layer and in its values, which explore in place of, or along with, the exploration
+/// strategy.
+pub type NoisyQNetworkDevice<
+    const STATE_SIZE: usize,
+    const ACTION_SIZE: usize,
+    const INNER_SIZE: usize,
+    Act = ReLU,
+> = (
+    Dense<STATE_SIZE, INNER_SIZE, Act>,
+    NoisyDense<INNER_SIZE, INNER_SIZE, Act>,
+    NoisyLinear<INNER_SIZE, ACTION_SIZE, f32, Cuda>,
+);
+
+/// Which prebuilt network a saved model is, written next to its parameters since a file of
+/// parameters may load as several networks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkInfo {
+    /// The name of the network to the train command: `plain`, `deep`, `norm`, `dropout`,
+    /// `dueling` or `noisy`.
+    pub kind: String,
+    /// Units in every hidden layer.
+    pub width: usize,
+    /// Activation of the hidden layers.
+    pub activation: String,
+}
+
+impl NetworkInfo {
+    /// A prebuilt network with hidden layers of `width` units and the given activation.
+    pub fn new(kind: &str, width: usize, activation: &str) -> Self {
+        NetworkInfo {
+            kind: kind.to_string(),
+            width,
+            activation: activation.to_string(),
+        }
+    }
+
+    /// The file describing the model saved at `model`.
+    pub fn path(model: &str) -> String {
+        format!("{}.json", model)
+    }
+
+    /// Writes the description of the model saved at `model`.
+    pub fn save(&self, model: &str) -> Result<(), String> {
+        let path = Self::path(model);
+        std::fs::write(&path, serde_json::to_string(self).unwrap())
+            .map_err(|e| format!("Could not save {}: {}", path, e))
+    }
+
+    /// Reads the description of the model saved at `model`.
+    pub fn load(model: &str) -> Result<Self, String> {
+        let path = Self::path(model);
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read {}: {}", path, e))?;
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid {}: {}", path, e))
+    }
+}
+
+/// Calls the generic function `f::<M>(args)`, written `with_network!(info, f(args))`, with `M`
+/// the prebuilt network `info: &NetworkInfo` describes, for the game's state and action sizes.
+/// Evaluates to the result of `f` in `Ok`, or to an `Err` if no such network is prebuilt: the
+/// kinds of the train command, with hidden layers of 64, 128 or 256 units and `relu` or `tanh`.
+macro_rules! with_network {
+    ($info:expr, $($f:ident)::+ ($($args:tt)*)) => {{
+        let info: &$crate::dqn::NetworkInfo = $info;
+        macro_rules! call {
+            ($network:ty) => {
+                Ok($($f)::+::<$network>($($args)*))
+            };
+        }
+        $crate::dqn::with_network!(@widths info; 64, 128, 256)
+    }};
+    (@widths $info:ident; $($width:literal),*) => {
+        match ($info.width, $info.activation.as_str()) {
+            $(
+                ($width, "relu") => {
+                    $crate::dqn::with_network!(@kinds $info, $width, ::dfdx::nn::modules::ReLU)
+                }
+                ($width, "tanh") => {
+                    $crate::dqn::with_network!(@kinds $info, $width, ::dfdx::nn::modules::Tanh)
+                }
+            )*
+            (width, activation) => Err(format!(
+                "No prebuilt network has {} hidden units with {}",
+                width, activation
+            )),
+        }
+    };
+    (@kinds $info:ident, $width:literal, $act:ty) => {{
+        use ::canasta_rl::canastautil::{ACTION_SIZE as A, STATE_SIZE as S};
+        use $crate::dqn::network::*;
+        match $info.kind.as_str() {
+            "plain" => call!(QNetworkDevice<S, A, $width, $act>),
+            "deep" => call!(DeepQNetworkDevice<S, A, $width, $act>),
+            "norm" => call!(NormQNetworkDevice<S, A, $width, $act>),
+            // Dropping a tenth of the hidden units
+            "dropout" => call!(DropoutQNetworkDevice<S, A, $width, 10, $act>),
+            "dueling" => call!(DuelingQNetworkDevice<S, A, $width, $act>),
+            // Usually with --epsilon 0, the noise exploring on its own
+            "noisy" => call!(NoisyQNetworkDevice<S, A, $width, $act>),
+            kind => Err(format!("Unknown network: {}", kind)),
+        }
+    }};
+}
+pub(crate) use with_network;
+
+/// What a `DQNAgentTrainer` needs from its network: building it, and evaluating states one at a
+/// time, by batch, and by batch while training, when dropout applies.
+pub trait QNetworkModel<const STATE_SIZE: usize, const ACTION_SIZE: usize>:
+    BuildModule<Cuda, f32>
+    + Clone
+    + Module<Tensor<Rank1<STATE_SIZE>, f32, Cuda>, Output = Tensor<Rank1<ACTION_SIZE>, f32, Cuda>>
+    + Module<
+        Tensor<Rank2<BATCH, STATE_SIZE>, f32, Cuda>,
+        Output = Tensor<Rank2<BATCH, ACTION_SIZE>, f32, Cuda>,
+    > + ModuleMut<
+        Tensor<Rank2<BATCH, STATE_SIZE>, f32, Cuda, OwnedTape<f32, Cuda>>,
+        Output = Tensor<Rank2<BATCH, ACTION_SIZE>, f32, Cuda, OwnedTape<f32, Cuda>>,
+    >
+{
+}
+
+impl<M, const STATE_SIZE: usize, const ACTION_SIZE: usize> QNetworkModel<STATE_SIZE, ACTION_SIZE>
+    for M
+where
+    M: BuildModule<Cuda, f32>
+        + Clone
+        + Module<Tensor<Rank1<STATE_SIZE>, f32, Cuda>, Output = Tensor<Rank1<ACTION_SIZE>, f32, Cuda>>
+        + Module<
+            Tensor<Rank2<BATCH, STATE_SIZE>, f32, Cuda>,
+            Output = Tensor<Rank2<BATCH, ACTION_SIZE>, f32, Cuda>,
+        > + ModuleMut<
+            Tensor<Rank2<BATCH, STATE_SIZE>, f32, Cuda, OwnedTape<f32, Cuda>>,
+            Output = Tensor<Rank2<BATCH, ACTION_SIZE>, f32, Cuda, OwnedTape<f32, Cuda>>,
+        >,
+{
+}
+
+/// The last layer of a dueling network. The value of an action is the value of the state plus
+/// the advantage of the action, minus the mean advantage so that the two are identifiable.
+#[derive(Clone, Debug)]
+pub struct DuelingHead<const IN: usize, const ACTION_SIZE: usize, E: Dtype, D: Storage<E>> {
+    pub value: Linear<IN, 1, E, D>,
+    pub advantage: Linear<IN, ACTION_SIZE, E, D>,
+}
+
+impl<const IN: usize, const ACTION_SIZE: usize, E: Dtype, D: Storage<E>> NonMutableModule
+    for DuelingHead<IN, ACTION_SIZE, E, D>
+{
+}
+
+impl<const IN: usize, const ACTION_SIZE: usize, D: Device<f32>> TensorCollection<f32, D>
+    for DuelingHead<IN, ACTION_SIZE, f32, D>
+{
+    type To<E2: Dtype, D2: Device<E2>> = DuelingHead<IN, ACTION_SIZE, E2, D2>;
+
+    fn iter_tensors<V: ModuleVisitor<Self, f32, D>>(
+        visitor: &mut V,
+    ) -> Result<Option<Self::To<V::E2, V::D2>>, V::Err> {
+        visitor.visit_fields(
+            (
+                Self::module("value", |s| &s.value, |s| &mut s.value),
+                Self::module("advantage", |s| &s.advantage, |s| &mut s.advantage),
+            ),
+            |(value, advantage)| DuelingHead { value, advantage },
+        )
+    }
+}
+
+impl<const IN: usize, const ACTION_SIZE: usize, E, D, T> Module<Tensor<Rank1<IN>, E, D, T>>
+    for DuelingHead<IN, ACTION_SIZE, E, D>
+where
+    E: Dtype,
+    D: Device<E>,
+    T: Tape<E, D> + Merge<T>,
+{
+    type Output = Tensor<Rank1<ACTION_SIZE>, E, D, T>;
+    type Error = D::Err;
+
+    fn try_forward(&self, x: Tensor<Rank1<IN>, E, D, T>) -> Result<Self::Output, D::Err> {
+        let advantage = self.advantage.try_forward(x.with_empty_tape())?;
+        let (advantage, tape) = advantage.split_tape();
+        let mean = advantage.clone().put_tape(tape).try_mean::<Rank0, _>()?;
+        let value = self.value.try_forward(x)?.try_sum::<Rank0, _>()?;
+        let shift = value
+            .try_sub(mean)?
+            .try_broadcast::<Rank1<ACTION_SIZE>, _>()?;
+        advantage.retaped::<T>().try_add(shift)
+    }
+}
+
+impl<const IN: usize, const ACTION_SIZE: usize, const B: usize, E, D, T>
+    Module<Tensor<Rank2<B, IN>, E, D, T>> for DuelingHead<IN, ACTION_SIZE, E, D>
+where
+    E: Dtype,
+    D: Device<E>,
+    T: Tape<E, D> + Merge<T>,
+{
+    type Output = Tensor<Rank2<B, ACTION_SIZE>, E, D, T>;
+    type Error = D::Err;
+
+    fn try_forward(&self, x: Tensor<Rank2<B, IN>, E, D, T>) -> Result<Self::Output, D::Err> {
+        let advantage = self.advantage.try_forward(x.with_empty_tape())?;
+        let (advantage, tape) = advantage.split_tape();
+        let mean = advantage.clone().put_tape(tape).try_mean::<Rank1<B>, _>()?;
+        let value = self.value.try_forward(x)?.try_sum::<Rank1<B>, _>()?;
+        let shift = value
+            .try_sub(mean)?
+            .try_broadcast::<Rank2<B, ACTION_SIZE>, _>()?;
+        advantage.retaped::<T>().try_add(shift)
+    }
+}
+
+/// A linear layer with noisy weights and bias, `mu + sigma * noise`, both `mu` and `sigma` being
+/// learned. The noise is factorised: one Gaussian sample per input and per output, stored in
+/// `noise_in` and `noise_out` and changed only by `resample_noise` and `disable_noise`, so that
+/// the layer is deterministic between two calls.
+#[derive(Clone, Debug)]
+pub struct NoisyLinear<const IN: usize, const OUT: usize, E: Dtype, D: Storage<E>> {
+    pub weight_mu: Tensor<Rank2<OUT, IN>, E, D>,
+    pub weight_sigma: Tensor<Rank2<OUT, IN>, E, D>,
+    pub bias_mu: Tensor<Rank1<OUT>, E, D>,
+    pub bias_sigma: Tensor<Rank1<OUT>, E, D>,
+    pub noise_in: Tensor<Rank1<IN>, E, D>,
+    pub noise_out: Tensor<Rank1<OUT>, E, D>,
+}
+
+// The initial sigma of a layer of one input, divided by the square root of the inputs otherwise
+const NOISY_SIGMA: f32 = 0.5;
+
+impl<const IN: usize, const OUT: usize, E: Dtype, D: Storage<E>> NonMutableModule
+    for NoisyLinear<IN, OUT, E, D>
+{
+}
+
+impl<const IN: usize, const OUT: usize, D: Device<f32>> TensorCollection<f32, D>
+    for NoisyLinear<IN, OUT, f32, D>
+{
+    type To<E2: Dtype, D2: Device<E2>> = NoisyLinear<IN, OUT, E2, D2>;
+
+    fn iter_tensors<V: ModuleVisitor<Self, f32, D>>(
+        visitor: &mut V,
+    ) -> Result<Option<Self::To<V::E2, V::D2>>, V::Err> {
+        visitor.visit_fields(
+            (
+                Self::tensor(
+                    "weight_mu",
+                    |s| &s.weight_mu,
+                    |s| &mut s.weight_mu,
+                    TensorOptions::reset_with(|t| {
+                        let b = 1.0 / (IN as f32).sqrt();
+                        t.try_fill_with_distr(Uniform::new(-b, b))
+                    }),
+                ),
+                Self::tensor(
+                    "weight_sigma",
+                    |s| &s.weight_sigma,
+                    |s| &mut s.weight_sigma,
+                    TensorOptions::reset_with(|t| {
+                        let sigma = NOISY_SIGMA / (IN as f32).sqrt();
+                        t.copy_from(&vec![sigma; OUT * IN]);
+                        Ok(())
+                    }),
+                ),
+                Self::tensor(
+                    "bias_mu",
+                    |s| &s.bias_mu,
+                    |s| &mut s.bias_mu,
+                    TensorOptions::reset_with(|t| {
+                        let b = 1.0 / (IN as f32).sqrt();
+                        t.try_fill_with_distr(Uniform::new(-b, b))
+                    }),
+                ),
+                Self::tensor(
+                    "bias_sigma",
+                    |s| &s.bias_sigma,
+                    |s| &mut s.bias_sigma,
+                    TensorOptions::reset_with(|t| {
+                        let sigma = NOISY_SIGMA / (IN as f32).sqrt();
+                        t.copy_from(&[sigma; OUT]);
+                        Ok(())
+                    }),
+                ),
+                Self::tensor(
+                    "noise_in",
+                    |s| &s.noise_in,
+                    |s| &mut s.noise_in,
+                    noise_options(),
+                ),
+                Self::tensor(
+                    "noise_out",
+                    |s| &s.noise_out,
+                    |s| &mut s.noise_out,
+                    noise_options(),
+                ),
+            ),
+            |(weight_mu, weight_sigma, bias_mu, bias_sigma, noise_in, noise_out)| NoisyLinear {
+                weight_mu,
+                weight_sigma,
+                bias_mu,
+                bias_sigma,
+                noise_in,
+                noise_out,
+            },
+        )
+    }
+}
+
+// Noise is not learned, and is reset to Gaussian samples scaled by sign(x) * sqrt(|x|)
+fn noise_options<const N: usize, D: Device<f32>>() -> TensorOptions<Rank1<N>, f32, D> {
+    TensorOptions::detached(|t| {
+        let samples = t.device().sample_normal_like(t).as_vec();
+        let noise: Vec<f32> = samples
+            .iter()
+            .map(|x| x.signum() * x.abs().sqrt())
+            .collect();
+        t.copy_from(&noise);
+        Ok(())
+    })
+}
+
+impl<const IN: usize, const OUT: usize, E: Dtype, D: Device<E>> NoisyLinear<IN, OUT, E, D> {
+    // The weight and bias with the current noise, on the tape `T`
+    #[allow(clippy::type_complexity)]
+    fn noisy_params<T: Tape<E, D> + Merge<T>>(
+        &self,
+    ) -> Result<(Tensor<Rank2<OUT, IN>, E, D, T>, Tensor<Rank1<OUT>, E, D, T>), D::Err> {
+        let noise = self
+            .noise_out
+            .clone()
+            .try_broadcast::<Rank2<OUT, IN>, _>()?
+            .try_mul(self.noise_in.clone().try_broadcast::<Rank2<OUT, IN>, _>()?)?;
+        let weight = self.weight_sigma.retaped::<T>().try_mul(noise)?;
+        let weight = self.weight_mu.retaped::<T>().try_add(weight)?;
+        let bias = self
+            .bias_sigma
+            .retaped::<T>()
+            .try_mul(self.noise_out.clone())?;
+        let bias = self.bias_mu.retaped::<T>().try_add(bias)?;
+        Ok((weight, bias))
+    }
+}
+
+impl<const IN: usize, const OUT: usize, E, D, T> Module<Tensor<Rank1<IN>, E, D, T>>
+    for NoisyLinear<IN, OUT, E, D>
+where
+    E: Dtype,
+    D: Device<E>,
+    T: Tape<E, D> + Merge<T>,
+{
+    type Output = Tensor<Rank1<OUT>, E, D, T>;
+    type Error = D::Err;
+
+    fn try_forward(&self, x: Tensor<Rank1<IN>, E, D, T>) -> Result<Self::Output, D::Err> {
+        let (weight, bias) = self.noisy_params::<T>()?;
+        x.try_matmul(weight.try_permute()?)?.try_add(bias)
+    }
+}
+
+impl<const IN: usize, const OUT: usize, const B: usize, E, D, T>
+    Module<Tensor<Rank2<B, IN>, E, D, T>> for NoisyLinear<IN, OUT, E, D>
+where
+    E: Dtype,
+    D: Device<E>,
+    T: Tape<E, D> + Merge<T>,
+{
+    type Output = Tensor<Rank2<B, OUT>, E, D, T>;
+    type Error = D::Err;
+
+    fn try_forward(&self, x: Tensor<Rank2<B, IN>, E, D, T>) -> Result<Self::Output, D::Err> {
+        let (weight, bias) = self.noisy_params::<T>()?;
+        x.try_matmul(weight.try_permute()?)?
+            .try_add(bias.try_broadcast::<Rank2<B, OUT>, _>()?)
+    }
+}
+
+/// Samples new noise in every `NoisyLinear` layer of `model`. Other layers are left as they are.
+pub fn resample_noise<D: Device<f32>, M: TensorCollection<f32, D>>(model: &mut M) {
+    M::iter_tensors(&mut RecursiveWalker {
+        m: (model, String::new()),
+        f: &mut SetNoise { on: true },
+    })
+    .unwrap();
+}
+
+/// Silences the noise of every `NoisyLinear` layer of `model`, which then uses its mean weights
+/// until the noise is resampled.
+pub fn disable_noise<D: Device<f32>, M: TensorCollection<f32, D>>(model: &mut M) {
+    M::iter_tensors(&mut RecursiveWalker {
+        m: (model, String::new()),
+        f: &mut SetNoise { on: false },
+    })
+    .unwrap();
+}
+
+// Resamples or zeroes the noise tensors, known by their names
+struct SetNoise {
+    on: bool,
+}
+
+impl<D: Device<f32>> TensorVisitor<f32, D> for SetNoise {
+    type Viewer = (ViewTensorMut, ViewTensorName);
+    type Err = D::Err;
+    type E2 = f32;
+    type D2 = D;
+
+    fn visit<S: Shape>(
+        &mut self,
+        opts: TensorOptions<S, f32, D>,
+        (t, name): (&mut Tensor<S, f32, D>, String),
+    ) -> Result<Option<Tensor<S, f32, D>>, Self::Err> {
+        if matches!(name.rsplit('.').next(), Some("noise_in" | "noise_out")) {
+            match self.on {
+                true => (opts.reset)(t)?,
+                false => t.try_fill_with_zeros()?,
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The largest difference between two rows of values once their own means are taken away
+    fn centered_difference<const B: usize, const N: usize>(
+        a: [[f32; N]; B],
+        b: [[f32; N]; B],
+    ) -> f32 {
+        let mean = |row: &[f32; N]| row.iter().sum::<f32>() / N as f32;
+        a.iter()
+            .zip(b.iter())
+            .flat_map(|(a, b)| {
+                let (mean_a, mean_b) = (mean(a), mean(b));
+                a.iter()
+                    .zip(b.iter())
+                    .map(move |(a, b)| ((a - mean_a) - (b - mean_b)).abs())
+            })
+            .fold(0.0, f32::max)
+    }
+
+    // A noisy layer on the CPU, with noise sampled
+    fn noisy_layer() -> (NoisyLinear<4, 3, f32, Cpu>, Tensor<Rank2<2, 4>, f32, Cpu>) {
+        let dev: Cpu = Default::default();
+        let mut layer = NoisyLinear::<4, 3, f32, Cpu>::build(&dev);
+        resample_noise(&mut layer);
+        let x = dev.sample_normal();
+        (layer, x)
+    }
+
+    #[test]
+    fn noise_stays_until_resampled() {
+        let (mut layer, x) = noisy_layer();
+        let first = layer.forward(x.clone()).array();
+        assert_eq!(layer.forward(x.clone()).array(), first);
+        resample_noise(&mut layer);
+        assert_ne!(layer.forward(x).array(), first);
+    }
+
+    #[test]
+    fn disabled_noise_leaves_the_mean_weights() {
+        let (mut layer, x) = noisy_layer();
+        disable_noise(&mut layer);
+        let mean = Linear::<4, 3, f32, Cpu> {
+            weight: layer.weight_mu.clone(),
+            bias: layer.bias_mu.clone(),
+        };
+        let expected = mean.forward(x.clone()).array();
+        let values = layer.forward(x).array();
+        for (row, expected) in values.iter().zip(expected.iter()) {
+            for (value, expected) in row.iter().zip(expected.iter()) {
+                assert!((value - expected).abs() < 1e-6);
+            }
+        }
+        assert_eq!(layer.noise_in.array(), [0.0; 4]);
+        assert_eq!(layer.noise_out.array(), [0.0; 3]);
+    }
+
+    #[test]
+    fn network_info_round_trips() {
+        let model = std::env::temp_dir()
+            .join(format!("canasta_rl_info_{}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        assert!(NetworkInfo::load(&model).is_err());
+        let info = NetworkInfo::new("dueling", 32, "tanh");
+        info.save(&model).unwrap();
+        assert_eq!(NetworkInfo::load(&model), Ok(info));
+        std::fs::remove_file(NetworkInfo::path(&model)).unwrap();
+    }
+
+    #[test]
+    fn dueling_values_differ_from_the_advantages_by_the_row_mean() {
+        let dev: Cpu = Default::default();
+        let head = DuelingHead::<4, 3, f32, Cpu>::build(&dev);
+        let x: Tensor<Rank2<2, 4>, f32, Cpu> = dev.sample_normal();
+        let values = head.forward(x.clone()).array();
+        let advantages = head.advantage.forward(x).array();
+        assert!(centered_difference(values, advantages) < 1e-6);
+    }
+}
@@ -0,0 +1,389 @@
+//! How a `DQNAgentTrainer` takes a gradient step: the optimizer, the schedule of its learning
+//! rate, and the clipping of the gradients.
+
+use std::str::FromStr;
+
+use dfdx::{
+    nn::{RecursiveWalker, TensorCollection, TensorOptions, TensorVisitor, ViewTensorRef},
+    optim::{Adam, AdamConfig, Momentum, RMSprop, RMSpropConfig, Sgd, SgdConfig},
+    prelude::*,
+};
+
+use super::DQNConfig;
+
+/// The optimizer of a `DQNAgentTrainer`, with its settings other than the learning rate and the
+/// weight decay.
+#[derive(Clone, Debug)]
+pub enum OptimizerKind {
+    /// Stochastic gradient descent, with momentum if given.
+    Sgd { momentum: Option<Momentum> },
+    /// Adam, with the decay rates of its estimates of the mean and variance of the gradients.
+    Adam { betas: [f64; 2], eps: f64 },
+    /// RMSprop, with the decay rate of its average of the squared gradients.
+    RMSprop {
+        alpha: f64,
+        eps: f64,
+        momentum: Option<f64>,
+        centered: bool,
+    },
+}
+
+impl Default for OptimizerKind {
+    fn default() -> Self {
+        OptimizerKind::Sgd {
+            momentum: Some(Momentum::Nesterov(0.9)),
+        }
+    }
+}
+
+impl FromStr for OptimizerKind {
+    type Err = String;
+
+    /// Parses `sgd`, `adam` or `rmsprop`, each with its usual settings.
+    fn from_str(s: &str) -> Result<OptimizerKind, String> {
+        match s {
+            "sgd" => Ok(OptimizerKind::default()),
+            "adam" => {
+                let AdamConfig { betas, eps, .. } = Default::default();
+                Ok(OptimizerKind::Adam { betas, eps })
+            }
+            "rmsprop" => {
+                let RMSpropConfig {
+                    alpha,
+                    eps,
+                    momentum,
+                    centered,
+                    ..
+                } = Default::default();
+                Ok(OptimizerKind::RMSprop {
+                    alpha,
+                    eps,
+                    momentum,
+                    centered,
+                })
+            }
+            _ => Err(format!("Unknown optimizer: {}", s)),
+        }
+    }
+}
+
+/// How the learning rate changes over the gradient steps, after the warmup.
+#[derive(Clone, Debug)]
+pub enum LrSchedule {
+    Constant,
+    /// Multiplied by `factor` every `every` steps, or never if `every` is 0.
+    Step {
+        every: usize,
+        factor: f64,
+    },
+    /// Annealed along a half cosine down to `min` over `steps` steps, then kept there.
+    Cosine {
+        steps: usize,
+        min: f64,
+    },
+}
+
+impl LrSchedule {
+    /// The learning rate at gradient step `step`, starting from `base`, when the first
+    /// `warmup_steps` steps raise it linearly to `base`.
+    pub fn learning_rate(&self, base: f64, warmup_steps: usize, step: usize) -> f64 {
+        if step < warmup_steps {
+            return base * (step + 1) as f64 / warmup_steps as f64;
+        }
+        let step = step - warmup_steps;
+        match self {
+            LrSchedule::Constant => base,
+            LrSchedule::Step { every, factor } => {
+                base * factor.powi(step.checked_div(*every).unwrap_or(0) as i32)
+            }
+            LrSchedule::Cosine { steps, min } => {
+                let progress = step.min(*steps) as f64 / *steps as f64;
+                min + (base - min) * (1.0 + (std::f64::consts::PI * progress).cos()) / 2.0
+            }
+        }
+    }
+}
+
+impl FromStr for LrSchedule {
+    type Err = String;
+
+    /// Parses `constant`, `step:EVERY:FACTOR` or `cosine:STEPS:MIN`.
+    fn from_str(s: &str) -> Result<LrSchedule, String> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let number = |i: usize| -> Result<f64, String> {
+            parts[i]
+                .parse()
+                .map_err(|_| format!("Invalid number: {}", parts[i]))
+        };
+        let steps = |i: usize| -> Result<usize, String> {
+            match parts[i].parse() {
+                Ok(steps) if steps > 0 => Ok(steps),
+                _ => Err(format!("Invalid number of steps: {}", parts[i])),
+            }
+        };
+        match parts[..] {
+            ["constant"] => Ok(LrSchedule::Constant),
+            ["step", _, _] => Ok(LrSchedule::Step {
+                every: steps(1)?,
+                factor: number(2)?,
+            }),
+            ["cosine", _, _] => Ok(LrSchedule::Cosine {
+                steps: steps(1)?,
+                min: number(2)?,
+            }),
+            _ => Err(format!("Unknown learning rate schedule: {}", s)),
+        }
+    }
+}
+
+enum Optim<M> {
+    Sgd(Sgd<M, f32, Cuda>),
+    Adam(Adam<M, f32, Cuda>),
+    RMSprop(RMSprop<M, f32, Cuda>),
+}
+
+/// The optimizer of a network `M`, following the settings of a `DQNConfig`.
+pub struct DQNOptimizer<M> {
+    optim: Optim<M>,
+    learning_rate: f64,
+    lr_schedule: LrSchedule,
+    warmup_steps: usize,
+    max_grad_norm: Option<f32>,
+}
+
+impl<M: TensorCollection<f32, Cuda>> DQNOptimizer<M> {
+    pub fn new(model: &M, config: &DQNConfig) -> DQNOptimizer<M> {
+        let lr = config.learning_rate;
+        let weight_decay = config.weight_decay;
+        let optim = match config.optimizer.clone() {
+            OptimizerKind::Sgd { momentum } => Optim::Sgd(Sgd::new(
+                model,
+                SgdConfig {
+                    lr,
+                    momentum,
+                    weight_decay,
+                },
+            )),
+            OptimizerKind::Adam { betas, eps } => Optim::Adam(Adam::new(
+                model,
+                AdamConfig {
+                    lr,
+                    betas,
+                    eps,
+                    weight_decay,
+                },
+            )),
+            OptimizerKind::RMSprop {
+                alpha,
+                eps,
+                momentum,
+                centered,
+            } => Optim::RMSprop(RMSprop::new(
+                model,
+                RMSpropConfig {
+                    lr,
+                    alpha,
+                    eps,
+                    momentum,
+                    centered,
+                    weight_decay,
+                },
+            )),
+        };
+        DQNOptimizer {
+            optim,
+            learning_rate: lr,
+            lr_schedule: config.lr_schedule.clone(),
+            warmup_steps: config.warmup_steps,
+            max_grad_norm: config.max_grad_norm,
+        }
+    }
+
+    /// Updates `model` from `grads` as gradient step `step`, clipping them first.
+    pub fn update(&mut self, model: &mut M, mut grads: Gradients<f32, Cuda>, step: usize) {
+        if let Some(max_norm) = self.max_grad_norm {
+            clip_grads(model, &mut grads, max_norm).unwrap();
+        }
+        let lr = self
+            .lr_schedule
+            .learning_rate(self.learning_rate, self.warmup_steps, step);
+        match &mut self.optim {
+            Optim::Sgd(sgd) => {
+                sgd.cfg.lr = lr;
+                sgd.update(model, &grads)
+            }
+            Optim::Adam(adam) => {
+                adam.cfg.lr = lr;
+                adam.update(model, &grads)
+            }
+            Optim::RMSprop(rmsprop) => {
+                rmsprop.cfg.lr = lr;
+                rmsprop.update(model, &grads)
+            }
+        }
+        .expect("Unused params");
+    }
+}
+
+// Scales the gradients of the parameters of `model` down in place, so that their global norm is
+// at most `max_norm`
+fn clip_grads<M, D>(model: &M, grads: &mut Gradients<f32, D>, max_norm: f32) -> Result<(), D::Err>
+where
+    M: TensorCollection<f32, D>,
+    D: Device<f32> + TensorToArray<Rank0, f32, Array = f32>,
+{
+    let mut norm = GradNorm {
+        grads,
+        sum_squares: 0.0,
+    };
+    M::iter_tensors(&mut RecursiveWalker {
+        m: model,
+        f: &mut norm,
+    })?;
+    let norm = norm.sum_squares.sqrt();
+    if norm <= max_norm {
+        return Ok(());
+    }
+    M::iter_tensors(&mut RecursiveWalker {
+        m: model,
+        f: &mut ScaleGrads {
+            grads,
+            scale: max_norm / norm,
+        },
+    })?;
+    Ok(())
+}
+
+// Sums the squares of the gradients of the parameters
+struct GradNorm<'a, D: Device<f32>> {
+    grads: &'a Gradients<f32, D>,
+    sum_squares: f32,
+}
+
+impl<D> TensorVisitor<f32, D> for GradNorm<'_, D>
+where
+    D: Device<f32> + TensorToArray<Rank0, f32, Array = f32>,
+{
+    type Viewer = ViewTensorRef;
+    type Err = D::Err;
+    type E2 = f32;
+    type D2 = D;
+
+    fn visit<S: Shape>(
+        &mut self,
+        opts: TensorOptions<S, f32, D>,
+        t: &Tensor<S, f32, D>,
+    ) -> Result<Option<Tensor<S, f32, D>>, Self::Err> {
+        if opts.do_gradient_update {
+            self.sum_squares += self.grads.get(t).square().sum::<Rank0, _>().array();
+        }
+        Ok(None)
+    }
+}
+
+// Multiplies the gradients of the parameters by `scale` in place
+struct ScaleGrads<'a, D: Device<f32>> {
+    grads: &'a mut Gradients<f32, D>,
+    scale: f32,
+}
+
+impl<D: Device<f32>> TensorVisitor<f32, D> for ScaleGrads<'_, D> {
+    type Viewer = ViewTensorRef;
+    type Err = D::Err;
+    type E2 = f32;
+    type D2 = D;
+
+    fn visit<S: Shape>(
+        &mut self,
+        opts: TensorOptions<S, f32, D>,
+        t: &Tensor<S, f32, D>,
+    ) -> Result<Option<Tensor<S, f32, D>>, Self::Err> {
+        if opts.do_gradient_update {
+            // dfdx only writes to gradients through its optimizer kernels, so the scaling is
+            // the plain SGD step grad - (1 - scale) * grad
+            let step = SgdConfig {
+                lr: f64::from(1.0 - self.scale),
+                momentum: None,
+                weight_decay: None,
+            };
+            let grad = self.grads.get_or_alloc_mut(t)?;
+            let (unscaled, mut velocity) = (grad.clone(), grad.clone());
+            t.device()
+                .sgd_kernel(&step, grad, &mut velocity, &unscaled)?;
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The global norm of the gradients of the parameters of `model`
+    fn grad_norm<M: TensorCollection<f32, Cpu>>(model: &M, grads: &Gradients<f32, Cpu>) -> f32 {
+        let mut norm = GradNorm {
+            grads,
+            sum_squares: 0.0,
+        };
+        M::iter_tensors(&mut RecursiveWalker {
+            m: model,
+            f: &mut norm,
+        })
+        .unwrap();
+        norm.sum_squares.sqrt()
+    }
+
+    #[test]
+    fn gradients_are_clipped_in_place() {
+        let dev: Cpu = Default::default();
+        let model = dev.build_module::<Linear<3, 2>, f32>();
+        let x: Tensor<Rank2<4, 3>, f32, Cpu> = dev.sample_normal();
+        let loss = model.forward(x.traced(model.alloc_grads())).square().sum();
+        let mut grads = loss.backward();
+        let norm = grad_norm(&model, &grads);
+        let weight = grads.get(&model.weight).array();
+
+        clip_grads(&model, &mut grads, 2.0 * norm).unwrap();
+        assert_eq!(grads.get(&model.weight).array(), weight);
+
+        clip_grads(&model, &mut grads, norm / 4.0).unwrap();
+        assert!((grad_norm(&model, &grads) - norm / 4.0).abs() < 1e-4 * norm);
+        let clipped = grads.get(&model.weight).array();
+        for (clipped, weight) in clipped.iter().flatten().zip(weight.iter().flatten()) {
+            assert!((clipped - weight / 4.0).abs() < 1e-5 * weight.abs().max(1.0));
+        }
+    }
+
+    #[test]
+    fn learning_rate_schedules() {
+        let step = LrSchedule::Step {
+            every: 10,
+            factor: 0.5,
+        };
+        assert_eq!(step.learning_rate(1.0, 0, 9), 1.0);
+        assert_eq!(step.learning_rate(1.0, 0, 25), 0.25);
+        let never = LrSchedule::Step {
+            every: 0,
+            factor: 0.5,
+        };
+        assert_eq!(never.learning_rate(1.0, 0, 25), 1.0);
+
+        let cosine = LrSchedule::Cosine {
+            steps: 100,
+            min: 0.1,
+        };
+        assert!((cosine.learning_rate(1.0, 0, 50) - 0.55).abs() < 1e-9);
+        assert!((cosine.learning_rate(1.0, 0, 500) - 0.1).abs() < 1e-9);
+
+        // Warmup comes first, then the schedule starts
+        assert_eq!(LrSchedule::Constant.learning_rate(1.0, 4, 1), 0.5);
+        assert_eq!(step.learning_rate(1.0, 4, 13), 1.0);
+        assert_eq!(step.learning_rate(1.0, 4, 14), 0.5);
+
+        assert!("step:0:0.5".parse::<LrSchedule>().is_err());
+        assert!(matches!(
+            "cosine:1000:0.01".parse(),
+            Ok(LrSchedule::Cosine { steps: 1000, .. })
+        ));
+    }
+}
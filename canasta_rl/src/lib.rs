@@ -89,9 +89,14 @@ use strategy::explore::ExplorationStrategy;
 use strategy::learn::LearningStrategy;
 use strategy::terminate::TerminationStrategy;
 
+pub mod canastautil;
+#[cfg(feature = "capi")]
+pub mod capi;
 #[cfg(feature = "dqn")]
 pub mod dqn;
 pub mod mdp;
+#[cfg(feature = "python")]
+pub mod python;
 pub mod strategy;
 
 /// An `AgentTrainer` can be trained for using a certain [Agent](mdp/trait.Agent.html). After
@@ -152,7 +157,8 @@ where
     }
 
     /// Trains this [AgentTrainer] using the given [ExplorationStrategy], [LearningStrategy] and
-    /// [Agent] until the [TerminationStrategy] decides to stop.
+    /// [Agent] until the [TerminationStrategy] decides to stop, which ends an episode of the
+    /// [ExplorationStrategy].
     pub fn train(
         &mut self,
         agent: &mut dyn Agent<S>,
@@ -160,9 +166,20 @@ where
         termination_strategy: &mut dyn TerminationStrategy<S>,
         exploration_strategy: &dyn ExplorationStrategy<S>,
     ) {
+        let mut rng = rand::thread_rng();
         loop {
             let s_t = agent.current_state().clone();
-            let action = exploration_strategy.pick_action(agent);
+            // The strategy picks among the actions of the state, unlearned ones being worth 0
+            let actions = s_t.actions();
+            let values: Vec<f64> = (actions.iter())
+                .map(|a| self.expected_value(&s_t, a).unwrap_or(0.0))
+                .collect();
+            let legal: Vec<bool> = (actions.iter())
+                .map(|a| s_t.check_legal_action(a.clone()))
+                .collect();
+            let index = exploration_strategy.pick_action(&s_t, &values, &legal, &mut rng);
+            let action = actions[index].clone();
+            agent.take_action(&action);
 
             // current action value
             let s_t_next = agent.current_state();
@@ -176,6 +193,7 @@ where
             self.q.entry(s_t).or_default().insert(action, v);
 
             if termination_strategy.should_stop(&s_t_next.clone()) {
+                exploration_strategy.end_episode();
                 break;
             }
         }
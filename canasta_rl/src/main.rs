@@ -1,46 +1,48 @@
-mod canastautil;
+mod bot;
 mod dqn;
 mod model_eval;
+mod play;
+mod replay;
+mod selfplay;
+mod server;
+mod watch;
 
-use canasta_rl::strategy::terminate::TerminationStrategy;
-use canastautil::GameState;
+use canasta_rl::canastautil;
+use canasta_rl::strategy::explore::{Boltzmann, EpsilonGreedy, Ucb};
 use dfdx::nn::ToDevice;
+use dfdx::optim::WeightDecay;
 use dfdx::prelude::*;
-use dqn::QNetworkDevice;
+use dqn::{QNetworkDevice, QNetworkModel};
+use std::thread;
 use std::{fs::File, fs::OpenOptions, io::Write};
-use std::{
-    sync::{Arc, Mutex},
-    thread,
-};
-
-use canasta_rl::strategy::explore::RandomExploration;
 
 const ACTION_SIZE: usize = canastautil::ACTION_SIZE;
 const STATE_SIZE: usize = canastautil::STATE_SIZE;
 const INNER_SIZE: usize = canastautil::INNER_SIZE;
 
-const PLAYERS_PER_TEAM: u8 = canastautil::PLAYERS_PER_TEAM;
-const TEAMS_COUNT: u8 = canastautil::TEAMS_COUNT;
-const DECKS: u8 = canastautil::DECKS;
-const HAND_SIZE: u8 = canastautil::HAND_SIZE;
+const MODEL_DIR: &str = "models";
 
-pub struct CanastaTerminator {}
+// The network trained by every seat, unless another is given to the train command
+type Network = QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>;
 
-impl CanastaTerminator {
-    pub fn new() -> CanastaTerminator {
-        CanastaTerminator {}
-    }
-}
+// Games stepped together by every env, their decisions evaluated in one batch
+const GAMES_PER_ENV: usize = 8;
 
-impl TerminationStrategy<GameState<1, 2>> for CanastaTerminator {
-    fn should_stop(&mut self, state: &GameState<1, 2>) -> bool {
-        state.game.finished
-    }
+// Bits of the SimHash UCB counts the states under, so at most 4096 keys of similar states
+const UCB_HASH_BITS: usize = 12;
+
+// How every trainer explores, unless the train command changes it
+#[derive(Clone)]
+enum Exploration {
+    EpsilonGreedy(EpsilonGreedy),
+    Boltzmann(Boltzmann),
+    // Counting the actions taken under the SimHash of the states, with this exploration constant
+    Ucb(f64),
 }
 
-impl TerminationStrategy<GameState<2, 2>> for CanastaTerminator {
-    fn should_stop(&mut self, state: &GameState<2, 2>) -> bool {
-        state.game.finished
+impl Default for Exploration {
+    fn default() -> Self {
+        Exploration::EpsilonGreedy(EpsilonGreedy::new(0.1))
     }
 }
 
@@ -50,7 +52,106 @@ enum RunType {
     Testing,
 }
 
-fn training() {
+/// Training from the command line:
+/// `canasta_rl train [NETWORK] [--width UNITS] [--activation NAME] [--optimizer NAME] [--lr RATE]
+/// [--weight-decay RATE] [--clip NORM] [--schedule SCHEDULE] [--warmup STEPS]
+/// [--epsilon EPSILON | --boltzmann TEMPERATURE | --ucb C]`, the settings not given being those of
+/// `training_config`, exploring with a constant epsilon of 0.1 by default. Networks are plain
+/// ones of `INNER_SIZE` units with `relu` unless given. The learning rate is
+/// 0.2 for SGD and 0.001 for Adam and RMSprop unless given.
+fn train(args: &[String]) {
+    if let Err(e) = train_args(args) {
+        println!("{}", e);
+        println!(
+            "Usage: canasta_rl train [NETWORK] [--width UNITS] [--activation NAME] \
+             [--optimizer NAME] [--lr RATE] [--weight-decay RATE] [--clip NORM] \
+             [--schedule SCHEDULE] [--warmup STEPS] \
+             [--epsilon EPSILON | --boltzmann TEMPERATURE | --ucb C]"
+        );
+        println!("Networks: plain, deep, norm, dropout, dueling, noisy");
+        println!("Widths: 64, 128, 256; activations: relu, tanh");
+        println!("Optimizers: sgd (learning rate 0.2), adam, rmsprop (learning rate 0.001)");
+        println!("Schedules: constant, step:EVERY:FACTOR, cosine:STEPS:MIN");
+        println!(
+            "Epsilons and temperatures: VALUE, linear:START:END:OVER, \
+             exponential:START:FACTOR:MIN, decaying per step or with :episode per episode"
+        );
+    }
+}
+
+fn train_args(args: &[String]) -> Result<(), String> {
+    let mut config = training_config();
+    let mut exploration = Exploration::default();
+    let mut network = None;
+    let mut width = INNER_SIZE;
+    let mut activation = "relu";
+    let mut learning_rate = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--optimizer" => config.optimizer = value()?.parse()?,
+            "--lr" => learning_rate = Some(number(value()?)?),
+            // Decoupled from the gradients, as in AdamW
+            "--weight-decay" => {
+                config.weight_decay = Some(WeightDecay::Decoupled(number(value()?)?))
+            }
+            "--clip" => config.max_grad_norm = Some(number(value()?)?),
+            "--schedule" => config.lr_schedule = value()?.parse()?,
+            "--warmup" => config.warmup_steps = number(value()?)?,
+            "--epsilon" => exploration = Exploration::EpsilonGreedy(value()?.parse()?),
+            "--boltzmann" => exploration = Exploration::Boltzmann(value()?.parse()?),
+            "--ucb" => exploration = Exploration::Ucb(number(value()?)?),
+            "--width" => width = number(value()?)?,
+            "--activation" => activation = value()?,
+            _ if network.is_none() => network = Some(arg.as_str()),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+    config.learning_rate =
+        learning_rate.unwrap_or_else(|| default_learning_rate(&config.optimizer));
+    let info = dqn::NetworkInfo::new(network.unwrap_or("plain"), width, activation);
+    dqn::with_network!(&info, training(config, exploration, info.clone()))
+}
+
+fn number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number: {}", value))
+}
+
+// The learning rate of an optimizer, unless the train command gives one. Adam and RMSprop scale
+// their steps by the size of the gradients and diverge at the rate SGD needs.
+fn default_learning_rate(optimizer: &dqn::OptimizerKind) -> f64 {
+    match optimizer {
+        dqn::OptimizerKind::Sgd { .. } => 0.2,
+        dqn::OptimizerKind::Adam { .. } | dqn::OptimizerKind::RMSprop { .. } => 1e-3,
+    }
+}
+
+// The settings of every trainer, unless the train command changes them
+fn training_config() -> dqn::DQNConfig {
+    dqn::DQNConfig {
+        gamma: 1.0,
+        learning_rate: default_learning_rate(&Default::default()),
+        target_update: dqn::TargetUpdate::Polyak(0.005),
+        double: true,
+        // Rewards only come at the end of a game, replay those transitions more
+        prioritized: Some(Default::default()),
+        ..Default::default()
+    }
+}
+
+// Trains the network `M`, which `info` describes next to the saved models
+fn training<M>(config: dqn::DQNConfig, exploration: Exploration, info: dqn::NetworkInfo)
+where
+    M: QNetworkModel<STATE_SIZE, ACTION_SIZE>
+        + Module<
+            Tensor<Rank2<GAMES_PER_ENV, STATE_SIZE>, f32, Cuda>,
+            Output = Tensor<Rank2<GAMES_PER_ENV, ACTION_SIZE>, f32, Cuda>,
+        > + 'static,
+    M::To<f32, Cpu>: TensorCollection<f32, Cpu> + Send,
+{
     const NUM_EPISODES_PER_EVAL: u32 = 25;
     const NUM_EVAL_EPISODES: u32 = 50;
     const NUM_ENVS: u8 = 6;
@@ -59,100 +160,118 @@ fn training() {
     let mut handles = Vec::new();
     let file = File::create("debug.txt").unwrap();
     drop(file);
+    std::fs::create_dir_all(MODEL_DIR).unwrap();
     for env_num in 1..NUM_ENVS + 1 {
-        let initial_state = Arc::new(Mutex::new(GameState::<PLAYERS_PER_TEAM, TEAMS_COUNT> {
-            game: canastautil::Game::new(PLAYERS_PER_TEAM, TEAMS_COUNT, 2, HAND_SIZE),
-        }));
-        let done: Arc<Mutex<[bool; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize]>> = Arc::new(
-            Mutex::new([false; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize]),
-        );
-        for handle_num in 0..PLAYERS_PER_TEAM * TEAMS_COUNT {
-            let agent_intial_state = Arc::clone(&initial_state);
-            let done_clone = Arc::clone(&done);
-            let agentthread = thread::spawn(move || {
-                let mut trainer = dqn::DQNAgentTrainer::<
-                    GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
-                    STATE_SIZE,
-                    ACTION_SIZE,
-                    INNER_SIZE,
-                >::new(1.0, 0.2);
-                for eval_ep in 1..NUM_EVAL_EPISODES + 1 {
-                    for ep in 1..NUM_EPISODES_PER_EVAL + 1 {
-                        let mut agent = canastautil::CanastaAgent {
-                            state: Arc::clone(&agent_intial_state),
-                            player_id: handle_num as u8,
-                        };
-                        trainer.train(
-                            &mut agent,
-                            &mut CanastaTerminator::new(),
-                            &RandomExploration::new(),
-                        );
-                        done_clone.lock().unwrap()[handle_num as usize] = true;
-                        if handle_num == 0 {
-                            while {
-                                let done_lock = done_clone.lock().unwrap();
-                                let mut out: bool = false;
-                                for i in 0..(PLAYERS_PER_TEAM * TEAMS_COUNT) as usize {
-                                    if done_lock[i] == false {
-                                        out = true;
-                                    }
-                                }
-                                out
-                            } {
-                                thread::sleep(std::time::Duration::from_nanos(1));
-                            }
-                            let mut state = agent_intial_state.lock().unwrap();
-                            println!(
-                                "Env: {}, Ep: {}, {:?}, {}",
-                                env_num,
-                                (eval_ep - 1) * NUM_EPISODES_PER_EVAL + ep,
-                                state.game.get_scores(),
-                                state.game.turn.total_turns / 4
-                            );
-                            state.game =
-                                canastautil::Game::new(PLAYERS_PER_TEAM, TEAMS_COUNT, DECKS, HAND_SIZE);
-                            let mut done_lock = done_clone.lock().unwrap();
-                            for i in 0..(PLAYERS_PER_TEAM * TEAMS_COUNT) as usize {
-                                done_lock[i] = false;
-                            }
-                        }
-                        while done_clone.lock().unwrap()[handle_num as usize] == true {
-                            std::thread::sleep(std::time::Duration::from_nanos(1));
-                        }
+        // Every env is driven by one thread, stepping its games for all the seats
+        let config = config.clone();
+        let exploration = exploration.clone();
+        let info = info.clone();
+        let envthread = thread::spawn(move || {
+            let mut games = selfplay::SelfPlay::<GAMES_PER_ENV>::new(u64::from(env_num));
+            let mut trainers: [selfplay::Trainer<M>; selfplay::PLAYERS] =
+                std::array::from_fn(|_| {
+                    let trainer = selfplay::Trainer::<M>::with_config(config.clone());
+                    match exploration.clone() {
+                        Exploration::EpsilonGreedy(strategy) => trainer.with_exploration(strategy),
+                        Exploration::Boltzmann(strategy) => trainer.with_exploration(strategy),
+                        Exploration::Ucb(c) => trainer.with_exploration(Ucb::hashed::<STATE_SIZE>(
+                            c,
+                            UCB_HASH_BITS,
+                            u64::from(env_num),
+                        )),
                     }
-                    //run some testing
-                    let mut scores : Vec<i16> = Vec::new();
+                });
+            let mut episode = 0;
+            for eval_ep in 1..NUM_EVAL_EPISODES + 1 {
+                for game in games.play(&mut trainers, NUM_EPISODES_PER_EVAL as usize) {
+                    episode += 1;
+                    println!(
+                        "Env: {}, Ep: {}, {:?}, {}",
+                        env_num,
+                        episode,
+                        game.get_scores(),
+                        game.turn.total_turns / 4
+                    );
+                }
+                //run some testing
+                for (handle_num, trainer) in trainers.iter().enumerate() {
+                    let mut scores: Vec<i16> = Vec::new();
                     for _ in 0..TESTING_GAMES {
                         let results = model_eval::test_model(trainer.export_learned_values());
                         scores.push(results[0] - results[1]);
                     }
-                    println!("TESTING RESULT {} : Env: {}, Agent: {}, Avg: {} \n", eval_ep, env_num, handle_num + 1, scores.iter().sum::<i16>() / TESTING_GAMES as i16);
+                    println!(
+                        "TESTING RESULT {} : Env: {}, Agent: {}, Avg: {} \n",
+                        eval_ep,
+                        env_num,
+                        handle_num + 1,
+                        scores.iter().sum::<i16>() / TESTING_GAMES as i16
+                    );
                     let mut file = OpenOptions::new().append(true).open("debug.txt").unwrap();
                     if DEBUG_FILE {
-                        file.write_fmt(format_args!("TESTING RESULT {} : Env: {}, Agent: {}, Avg: {} \n", eval_ep, env_num, handle_num + 1, scores.iter().sum::<i16>() / TESTING_GAMES as i16)).unwrap();
+                        file.write_fmt(format_args!(
+                            "TESTING RESULT {} : Env: {}, Agent: {}, Avg: {} \n",
+                            eval_ep,
+                            env_num,
+                            handle_num + 1,
+                            scores.iter().sum::<i16>() / TESTING_GAMES as i16
+                        ))
+                        .unwrap();
                     }
                 }
+            }
+            let dev: Cpu = Default::default();
+            let mut models = Vec::new();
+            for (handle_num, trainer) in trainers.iter().enumerate() {
+                let path = format!(
+                    "{}/env{}_agent{}.safetensors",
+                    MODEL_DIR, env_num, handle_num
+                );
+                trainer.save_model(&path).unwrap();
+                info.save(&path).unwrap();
                 let learned_values = trainer.export_learned_values();
-                let dev: Cpu = Default::default();
-                (env_num, handle_num, learned_values.to_device(&dev).clone())
-            });
-            handles.push(agentthread);
-            println!("Thread Spawned: {}, {}", env_num, handle_num);
-        }
+                models.push((
+                    env_num,
+                    handle_num as u8,
+                    learned_values.to_device(&dev).clone(),
+                ));
+            }
+            models
+        });
+        handles.push(envthread);
+        println!("Thread Spawned: {}", env_num);
     }
     let dev: Cuda = Default::default();
-    let mut models: Vec<(u8, u8, QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>)> = Vec::new();
+    let mut models = Vec::new();
     for handle in handles {
-        let out = handle.join().unwrap();
-        models.push((out.0, out.1, out.2.to_device(&dev)));
+        for out in handle.join().unwrap() {
+            models.push((out.0, out.1, out.2.to_device(&dev)));
+        }
     }
 }
 
 const RUN_TYPE: RunType = RunType::Training;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        match args[1].as_str() {
+            "eval" => model_eval::run(&args[2..]),
+            "play" => play::run(&args[2..]),
+            "replay" => replay::run(&args[2..]),
+            "serve" => server::run(&args[2..]),
+            "train" => train(&args[2..]),
+            "watch" => watch::run(&args[2..]),
+            _ => println!(
+                "Unknown command: {}\nCommands: eval, play, replay, serve, train, watch",
+                args[1]
+            ),
+        }
+        return;
+    }
     if RUN_TYPE == RunType::Training {
-        training();
+        let info = dqn::NetworkInfo::new("plain", INNER_SIZE, "relu");
+        training::<Network>(training_config(), Exploration::default(), info);
     } else if RUN_TYPE == RunType::Testing {
         let scores = model_eval::play_random_game();
         println!("{:?}", scores);
@@ -24,6 +24,8 @@ pub trait State: Eq + Hash + Clone {
     }
     fn check_legal(&self, _play: usize) -> bool;
     fn check_legal_action(&self, _action: Self::A) -> bool;
+    /// Legality of every action index, computed in one pass.
+    fn legal_mask(&self) -> Vec<bool>;
 }
 
 /// An `Agent` is something which hold a certain state, and is able to take actions from that
@@ -41,3 +43,73 @@ pub trait Agent<S: State> {
         action
     }
 }
+
+/// A Gym-style environment in which players take turns. Unlike an `Agent`, it does not act for a
+/// single player: whoever is to act is given by `current_player`, so one loop can drive every
+/// seat.
+pub trait Environment {
+    /// What the player to act observes.
+    type Obs;
+    /// Action type of this `Environment`.
+    type A;
+    /// Additional information returned by `step`.
+    type Info;
+
+    /// Starts a new episode, from `seed` if given, and returns the first observation.
+    fn reset(&mut self, seed: Option<u64>) -> Self::Obs;
+    /// Takes `action` for the current player. Returns the observation of the player to act next,
+    /// the reward of the player who acted, whether the episode is over and additional
+    /// information.
+    fn step(&mut self, action: &Self::A) -> (Self::Obs, f64, bool, Self::Info);
+    /// The player to act.
+    fn current_player(&self) -> usize;
+    /// The actions the current player may take.
+    fn legal_actions(&self) -> Vec<Self::A>;
+}
+
+/// `N` environments stepped in lockstep, so that the players to act in all of them can be
+/// evaluated together, e.g. in one batched forward pass.
+///
+/// An environment whose episode ended keeps its last observation until it is `reset`.
+pub struct VecEnv<E: Environment, const N: usize> {
+    envs: [E; N],
+    observations: [E::Obs; N],
+}
+
+impl<E: Environment, const N: usize> VecEnv<E, N> {
+    /// Resets every environment in `envs`, the i-th one from `seeds[i]`.
+    pub fn new(mut envs: [E; N], seeds: [Option<u64>; N]) -> Self {
+        let observations = std::array::from_fn(|i| envs[i].reset(seeds[i]));
+        VecEnv { envs, observations }
+    }
+
+    pub fn envs(&self) -> &[E; N] {
+        &self.envs
+    }
+
+    /// What the player to act observes in every environment.
+    pub fn observations(&self) -> &[E::Obs; N] {
+        &self.observations
+    }
+
+    /// The player to act in every environment.
+    pub fn current_players(&self) -> [usize; N] {
+        std::array::from_fn(|i| self.envs[i].current_player())
+    }
+
+    /// Starts a new episode in the i-th environment, from `seed` if given.
+    pub fn reset(&mut self, i: usize, seed: Option<u64>) {
+        self.observations[i] = self.envs[i].reset(seed);
+    }
+
+    /// Takes `actions[i]` in the i-th environment, for every environment. Returns, by
+    /// environment, the reward of the player who acted, whether the episode is over and the
+    /// additional information of `Environment::step`.
+    pub fn step(&mut self, actions: &[E::A; N]) -> [(f64, bool, E::Info); N] {
+        std::array::from_fn(|i| {
+            let (observation, reward, done, info) = self.envs[i].step(&actions[i]);
+            self.observations[i] = observation;
+            (reward, done, info)
+        })
+    }
+}
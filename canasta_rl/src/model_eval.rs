@@ -1,11 +1,9 @@
-#[path = "canastautil.rs"]
-mod canastautil;
-#[path = "dqn.rs"]
-mod dqn;
-use canasta_rl::mdp::{Agent, State};
-use canastautil::Action;
-use dqn::QNetworkDevice;
-use rand::seq::SliceRandom;
+use crate::bot::ExternalAgent;
+use crate::canastautil::notation::GameRecord;
+use crate::canastautil::{self, Action};
+use crate::dqn::{self, NetworkInfo, QNetworkModel};
+use std::fs::OpenOptions;
+use std::io::Write;
 
 const STATE_SIZE: usize = canastautil::STATE_SIZE;
 const ACTION_SIZE: usize = canastautil::ACTION_SIZE;
@@ -16,50 +14,101 @@ const TEAMS_COUNT: u8 = canastautil::TEAMS_COUNT;
 const DECKS: u8 = canastautil::DECKS;
 const HAND_SIZE: u8 = canastautil::HAND_SIZE;
 
-trait CanastaAgent {
+pub trait CanastaAgent {
     fn get_action(
         &self,
         state: &canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
     ) -> canastautil::Play;
+    fn name(&self) -> String;
 }
 
 #[derive(Clone, Copy)]
-struct RandomAgent {}
+pub struct RandomAgent {}
 
 impl CanastaAgent for RandomAgent {
     fn get_action(
         &self,
         state: &canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
     ) -> canastautil::Play {
-        let mut possible_plays: Vec<canastautil::Play> = Vec::new();
-        for play in canastautil::Play::iterator() {
-            if state.game.check_legal(*play) {
-                possible_plays.push(*play);
-            }
-        }
-        //choose randomly from possible_plays without shuffling
-        let chosen_play = possible_plays.choose(&mut rand::thread_rng());
-        match chosen_play {
-            Some(play) => return *play,
-            None => panic!("No legal plays"),
-        }
+        canastautil::random_legal_action(&state.game).play
+    }
+    fn name(&self) -> String {
+        "RandomAgent".to_string()
+    }
+}
+
+type Trainer<M> = dqn::DQNAgentTrainer<
+    canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
+    STATE_SIZE,
+    ACTION_SIZE,
+    INNER_SIZE,
+    M,
+>;
+
+// A trainer of any network, queried for the values of actions
+trait ActionValues {
+    fn q_values(
+        &self,
+        state: &canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
+    ) -> [f32; ACTION_SIZE];
+    fn best_action(
+        &self,
+        state: &canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
+    ) -> Option<Action>;
+}
+
+impl<M: QNetworkModel<STATE_SIZE, ACTION_SIZE>> ActionValues for Trainer<M> {
+    fn q_values(
+        &self,
+        state: &canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
+    ) -> [f32; ACTION_SIZE] {
+        self.expected_value(state)
+    }
+    fn best_action(
+        &self,
+        state: &canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
+    ) -> Option<Action> {
+        Trainer::best_action(self, state)
     }
 }
 
-struct TrainedAgent {
-    trainer: dqn::DQNAgentTrainer<
-        canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
-        STATE_SIZE,
-        ACTION_SIZE,
-        INNER_SIZE,
-    >,
+/// A trained model playing greedily, without the noise of noisy networks.
+pub struct TrainedAgent {
+    trainer: Box<dyn ActionValues>,
 }
 
 impl TrainedAgent {
-    pub fn new(model: QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>) -> Self {
-        let mut trainer = dqn::DQNAgentTrainer::new(0.99, 1e-3);
+    pub fn new<M: QNetworkModel<STATE_SIZE, ACTION_SIZE> + 'static>(model: M) -> Self {
+        let mut trainer = Trainer::<M>::new(0.99, 1e-3);
         trainer.import_model(model);
-        Self { trainer: trainer }
+        trainer.disable_noise();
+        Self {
+            trainer: Box::new(trainer),
+        }
+    }
+    /// Loads a model saved at the end of training as the network its description, written next
+    /// to it, names, with the width and activation it was trained with.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let info = NetworkInfo::load(path)?;
+        dqn::with_network!(&info, TrainedAgent::load_as(path))
+            .map_err(|e| format!("Cannot load {}: {}", path, e))?
+    }
+    fn load_as<M: QNetworkModel<STATE_SIZE, ACTION_SIZE> + 'static>(
+        path: &str,
+    ) -> Result<Self, String> {
+        let mut trainer = Trainer::<M>::new(0.99, 1e-3);
+        trainer.load_model(path)?;
+        trainer.disable_noise();
+        Ok(Self {
+            trainer: Box::new(trainer),
+        })
+    }
+    /// The value the model assigns to every action, by action index.
+    pub fn q_values(
+        &self,
+        state: &canastautil::GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
+    ) -> [f32; ACTION_SIZE] {
+        self.trainer.q_values(state)
     }
 }
 
@@ -74,31 +123,160 @@ impl CanastaAgent for TrainedAgent {
             None => panic!("No legal plays"),
         }
     }
+    fn name(&self) -> String {
+        "TrainedAgent".to_string()
+    }
 }
 
-fn run_game(agents: [&dyn CanastaAgent; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize]) -> Vec<i16> {
+// Plays a game between `agents`, appending its record to the file at `record` if given
+fn run_game(
+    agents: [&dyn CanastaAgent; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize],
+    record_file: Option<&str>,
+) -> Vec<i16> {
     let mut game = canastautil::Game::new(PLAYERS_PER_TEAM, TEAMS_COUNT, DECKS, HAND_SIZE);
     let mut possible_plays: Vec<canastautil::Play> = Vec::new();
     for play in canastautil::Play::iterator() {
         possible_plays.push(*play);
     }
 
+    let mut record = GameRecord::new(&game, agents.iter().map(|agent| agent.name()).collect());
+
     while !game.finished {
         let state = canastautil::GameState { game: game.clone() };
         let action = agents[game.turn.get() as usize].get_action(&state);
         game.execute_play(action);
+        record.push(action);
+    }
+    record.finish(&game);
+    if let Some(path) = record_file {
+        if let Err(e) = save_record(path, &record) {
+            println!("{}", e);
+        }
     }
     game.get_scores()
 }
 
+/// Appends `record` to the file at `path`.
+pub fn save_record(path: &str, record: &GameRecord) -> Result<(), String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(format!("{}\n", record).as_bytes()))
+        .map_err(|e| format!("Could not record the game to {}: {}", path, e))
+}
+
+/// Plays `games` games between `agents`, returning the average score of every seat. The record of
+/// every game is appended to the file at `record_file` if given.
+pub fn evaluate(
+    agents: [&dyn CanastaAgent; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize],
+    games: usize,
+    record_file: Option<&str>,
+) -> Vec<f64> {
+    let mut totals: Vec<f64> = vec![0.0; agents.len()];
+    for _ in 0..games {
+        for (total, score) in totals.iter_mut().zip(run_game(agents, record_file)) {
+            *total += score as f64;
+        }
+    }
+    totals.iter().map(|total| total / games as f64).collect()
+}
+
+// Builds an agent from `random`, `model:PATH` or `bot:COMMAND`
+fn agent(spec: &str) -> Result<Box<dyn CanastaAgent>, String> {
+    match spec.split_once(':') {
+        _ if spec == "random" => Ok(Box::new(RandomAgent {})),
+        Some(("model", path)) => Ok(Box::new(TrainedAgent::load(path)?)),
+        Some(("bot", command)) => Ok(Box::new(ExternalAgent::spawn(command)?)),
+        _ => Err(format!("Unknown agent: {}", spec)),
+    }
+}
+
+/// Evaluation from the command line:
+/// `canasta_rl eval [--games N] [--record FILE] AGENT AGENT AGENT AGENT`, with one agent per seat,
+/// each being `random`, `model:PATH` or `bot:COMMAND`. Games are recorded only with `--record`.
+pub fn run(args: &[String]) {
+    if let Err(e) = evaluate_args(args) {
+        println!("{}", e);
+        println!("Usage: canasta_rl eval [--games N] [--record FILE] AGENT AGENT AGENT AGENT");
+        println!("Agents: random, model:PATH, bot:COMMAND");
+    }
+}
+
+fn evaluate_args(args: &[String]) -> Result<(), String> {
+    let mut games: usize = 100;
+    let mut record_file: Option<&str> = None;
+    let mut agents: Vec<Box<dyn CanastaAgent>> = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--games" {
+            let value = args.next().ok_or("Missing value for --games")?;
+            games = match value.parse() {
+                Ok(games) if games > 0 => games,
+                _ => return Err(format!("Invalid number of games: {}", value)),
+            };
+        } else if arg == "--record" {
+            record_file = Some(args.next().ok_or("Missing value for --record")?);
+        } else {
+            agents.push(agent(arg)?);
+        }
+    }
+    let seats: [&dyn CanastaAgent; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize] = agents
+        .iter()
+        .map(|agent| agent.as_ref())
+        .collect::<Vec<&dyn CanastaAgent>>()
+        .try_into()
+        .map_err(|_| format!("Expected {} agents", PLAYERS_PER_TEAM * TEAMS_COUNT))?;
+
+    let averages = evaluate(seats, games, record_file);
+    println!("Average scores over {} games:", games);
+    for (i, average) in averages.iter().enumerate() {
+        println!("  Seat {} ({}): {:.1}", i, seats[i].name(), average);
+    }
+    Ok(())
+}
+
 pub fn play_random_game() -> Vec<i16> {
-    run_game([&RandomAgent {}; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize])
+    run_game([&RandomAgent {}; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize], None)
 }
 
-pub fn test_model(raw_model: QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>) -> Vec<i16> {
+pub fn test_model<M: QNetworkModel<STATE_SIZE, ACTION_SIZE> + 'static>(raw_model: M) -> Vec<i16> {
     let model = TrainedAgent::new(raw_model);
     let mut models: [&dyn CanastaAgent; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize] = [&RandomAgent {}; (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize];
     models[0] = &model;    
     models[2] = &model;
-    run_game(models)
+    run_game(models, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_load_only_as_a_prebuilt_network() {
+        let path = std::env::temp_dir()
+            .join(format!("canasta_rl_model_{}", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        assert!(TrainedAgent::load(&path).is_err());
+
+        NetworkInfo::new("plain", INNER_SIZE + 1, "relu").save(&path).unwrap();
+        let error = TrainedAgent::load(&path).err().unwrap();
+        assert!(error.contains("hidden units"), "{}", error);
+
+        NetworkInfo::new("plain", 64, "sigmoid").save(&path).unwrap();
+        let error = TrainedAgent::load(&path).err().unwrap();
+        assert!(error.contains("hidden units with sigmoid"), "{}", error);
+
+        NetworkInfo::new("wide", 64, "tanh").save(&path).unwrap();
+        let error = TrainedAgent::load(&path).err().unwrap();
+        assert!(error.contains("Unknown network"), "{}", error);
+
+        // A prebuilt network is dispatched to, and only lacks its weights
+        NetworkInfo::new("dueling", 256, "tanh").save(&path).unwrap();
+        let error = TrainedAgent::load(&path).err().unwrap();
+        assert!(!error.contains("hidden units"), "{}", error);
+        assert!(!error.contains("Unknown network"), "{}", error);
+        std::fs::remove_file(NetworkInfo::path(&path)).unwrap();
+    }
 }
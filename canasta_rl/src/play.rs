@@ -0,0 +1,130 @@
+//! Play a game against the agents from the terminal.
+//!
+//! Usage: `canasta_rl play [--seat N] [--model PATH] [--record FILE]`
+//!
+//! A human takes seat `N` (0 by default) and every other seat is played by the model at `PATH`,
+//! or by a `RandomAgent` if no model is given. The record of the game is appended to `FILE` if
+//! given. Plays are entered in notation (e.g. `D`, `M:K`,
+//! `X:5`) or by their number in the menu of legal plays.
+
+use crate::canastautil::notation::GameRecord;
+use crate::canastautil::{self, Game, GameState, Play};
+use crate::model_eval::{self, CanastaAgent, RandomAgent, TrainedAgent};
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "Enter a play in notation (D, P, M:K, W:7, X:5, OUT) or its number in the \
+                    menu, [?] to show the table again or [q] to quit";
+
+pub fn run(args: &[String]) {
+    if let Err(e) = play(args) {
+        println!("{}", e);
+        println!("Usage: canasta_rl play [--seat N] [--model PATH] [--record FILE]");
+    }
+}
+
+fn play(args: &[String]) -> Result<(), String> {
+    let players_count = canastautil::PLAYERS_PER_TEAM * canastautil::TEAMS_COUNT;
+    let mut seat: u8 = 0;
+    let mut model_path: Option<&str> = None;
+    let mut record_file: Option<&str> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seat" => {
+                let value = args.next().ok_or("Missing value for --seat")?;
+                seat = match value.parse() {
+                    Ok(seat) if seat < players_count => seat,
+                    _ => return Err(format!("Invalid seat: {}", value)),
+                };
+            }
+            "--model" => model_path = Some(args.next().ok_or("Missing value for --model")?),
+            "--record" => record_file = Some(args.next().ok_or("Missing value for --record")?),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    let agent: Box<dyn CanastaAgent> = match model_path {
+        Some(model_path) => Box::new(TrainedAgent::load(model_path)?),
+        None => Box::new(RandomAgent {}),
+    };
+
+    let mut game = Game::new(
+        canastautil::TEAMS_COUNT,
+        canastautil::PLAYERS_PER_TEAM,
+        canastautil::DECKS,
+        canastautil::HAND_SIZE,
+    );
+    let names: Vec<String> = (0..players_count)
+        .map(|i| if i == seat { "Human".to_string() } else { agent.name() })
+        .collect();
+    let mut record = GameRecord::new(&game, names.clone());
+
+    println!("{}", HELP);
+    print!("\n{}", game.seat_view(seat));
+    while !game.finished {
+        let turn = game.turn.get();
+        let play = if turn == seat {
+            match ask(&game, seat)? {
+                Some(play) => play,
+                None => return Ok(()),
+            }
+        } else {
+            agent.get_action(&GameState { game: game.clone() })
+        };
+        game.execute_play(play);
+        record.push(play);
+        println!("\nSeat {} ({}): {} ({})", turn, names[turn as usize], play, play.name());
+        print!("{}", game.seat_view(seat));
+    }
+
+    record.finish(&game);
+    if let Some(path) = record_file {
+        if let Err(e) = model_eval::save_record(path, &record) {
+            println!("{}", e);
+        }
+    }
+    println!("\nFinal scores:");
+    for (i, score) in game.get_scores().iter().enumerate() {
+        println!("  Seat {} ({}): {}", i, names[i], score);
+    }
+    Ok(())
+}
+
+// Asks the human for a legal play, returns None if they quit
+fn ask(game: &Game, seat: u8) -> Result<Option<Play>, String> {
+    let legal: Vec<Play> = Play::iterator()
+        .zip(game.legal_mask().iter())
+        .filter(|(_, legal)| **legal)
+        .map(|(play, _)| *play)
+        .collect();
+    let stdin = io::stdin();
+    loop {
+        println!("Your plays:");
+        for (i, play) in legal.iter().enumerate() {
+            println!("  {:>2}) {:<5} {}", i + 1, play.to_string(), play.name());
+        }
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Ok(None);
+        }
+        let input = line.trim();
+        let play = match input {
+            "q" => return Ok(None),
+            "?" | "" => {
+                print!("\n{}", game.seat_view(seat));
+                continue;
+            }
+            _ => match input.parse::<usize>() {
+                Ok(i) if i >= 1 && i <= legal.len() => Ok(legal[i - 1]),
+                Ok(i) => Err(format!("There is no play {}", i)),
+                Err(_) => input.parse::<Play>(),
+            },
+        };
+        match play {
+            Ok(play) if game.check_legal(play) => return Ok(Some(play)),
+            Ok(play) => println!("{} is not legal right now", play),
+            Err(e) => println!("{}\n{}", e, HELP),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Python bindings, built with `maturin` from `pyproject.toml`.
+//!
+//! The `canasta_rl._canasta` extension module exposes the engine as `Game`. The pure Python
+//! `canasta_rl.env` module wraps it as a PettingZoo AEC environment.
+
+use numpy::PyArray1;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::canastautil::{
+    self, Action, GameState, Play, ACTION_SIZE, PLAYERS_PER_TEAM, STATE_SIZE, TEAMS_COUNT,
+};
+
+const PLAYERS: u8 = PLAYERS_PER_TEAM * TEAMS_COUNT;
+
+/// A game of Canasta between four seats, partners sitting opposite each other.
+#[pyclass(name = "Game", module = "canasta_rl._canasta")]
+pub struct PyGame {
+    game: canastautil::Game,
+}
+
+fn deal(seed: Option<u64>) -> canastautil::Game {
+    canastautil::Game::new_seeded(
+        TEAMS_COUNT,
+        PLAYERS_PER_TEAM,
+        canastautil::DECKS,
+        canastautil::HAND_SIZE,
+        seed.unwrap_or_else(rand::random),
+    )
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    #[pyo3(signature = (seed=None))]
+    fn new(seed: Option<u64>) -> Self {
+        PyGame { game: deal(seed) }
+    }
+
+    /// Deals a new game, from `seed` if given.
+    #[pyo3(signature = (seed=None))]
+    fn reset(&mut self, seed: Option<u64>) {
+        self.game = deal(seed);
+    }
+
+    /// The seat to play.
+    #[getter]
+    fn current_player(&self) -> u8 {
+        self.game.turn.get()
+    }
+
+    #[getter]
+    fn finished(&self) -> bool {
+        self.game.finished
+    }
+
+    /// The seed the game was dealt from.
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.game.get_seed()
+    }
+
+    /// Legality of every action of the seat to play, as a boolean array.
+    fn legal_mask<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<bool>> {
+        PyArray1::from_slice(py, &self.game.legal_mask())
+    }
+
+    /// The encoding of the game seen by the seat to play, as used for training.
+    fn observation<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        let encoded: [f32; STATE_SIZE] = GameState::<PLAYERS_PER_TEAM, TEAMS_COUNT> {
+            game: self.game.clone(),
+        }
+        .into();
+        PyArray1::from_slice(py, &encoded)
+    }
+
+    /// Makes `action` for the seat to play, returning the reward of that seat and whether the
+    /// game is over. Raises `ValueError` if the action is not legal.
+    fn step(&mut self, action: usize) -> PyResult<(f64, bool)> {
+        if action >= ACTION_SIZE {
+            return Err(PyValueError::new_err(format!("No action {}", action)));
+        }
+        if self.game.finished {
+            return Err(PyValueError::new_err("The game is over"));
+        }
+        let play = Action::from(action).play;
+        if !self.game.check_legal(play) {
+            return Err(PyValueError::new_err(format!(
+                "{} is not legal",
+                play.name()
+            )));
+        }
+        let seat = self.game.turn.get();
+        self.game.execute_play(play);
+        Ok((self.rewards()[seat as usize], self.game.finished))
+    }
+
+    /// Scores by seat.
+    fn scores(&self) -> Vec<i16> {
+        self.game.get_scores()
+    }
+
+    /// Rewards by seat: once the game is over, the score of the seat's team minus the best score
+    /// of the other teams, zero before that.
+    fn rewards(&self) -> Vec<f64> {
+        self.game.rewards()
+    }
+
+    fn __str__(&self) -> String {
+        self.game.to_string()
+    }
+}
+
+/// The name of an action, e.g. "Discard Joker".
+#[pyfunction]
+fn action_name(action: usize) -> PyResult<String> {
+    if action >= ACTION_SIZE {
+        return Err(PyValueError::new_err(format!("No action {}", action)));
+    }
+    Ok(Play::from_index(action).name().to_string())
+}
+
+#[pymodule]
+fn _canasta(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGame>()?;
+    m.add_function(wrap_pyfunction!(action_name, m)?)?;
+    m.add("ACTION_SIZE", ACTION_SIZE)?;
+    m.add("STATE_SIZE", STATE_SIZE)?;
+    m.add("PLAYERS", PLAYERS)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use numpy::PyArrayMethods;
+
+    // Run with `cargo test --features python`, the arrays need numpy installed
+    #[test]
+    fn game_plays_through_the_bindings() {
+        Python::initialize();
+        Python::attach(|py| {
+            let mut game = PyGame::new(Some(7));
+            game.reset(Some(7));
+            assert_eq!(game.seed(), 7);
+            assert_eq!(game.observation(py).len().unwrap(), STATE_SIZE);
+            assert!(game.step(ACTION_SIZE).is_err());
+
+            while !game.finished() {
+                let seat = game.current_player() as usize;
+                let mask = game.legal_mask(py).to_vec().unwrap();
+                assert_eq!(mask, game.game.legal_mask().to_vec());
+                let action = mask.iter().position(|legal| *legal).unwrap();
+                let illegal = mask.iter().position(|legal| !*legal).unwrap();
+                assert!(game.step(illegal).is_err());
+                let (reward, done) = game.step(action).unwrap();
+                assert_eq!(reward, game.rewards()[seat]);
+                assert_eq!(done, game.finished());
+            }
+            assert!(game.step(0).is_err());
+            let rewards = game.rewards();
+            assert_eq!(rewards[0], rewards[2]);
+            assert_eq!(rewards[0], -rewards[1]);
+        });
+    }
+}
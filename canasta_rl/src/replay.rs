@@ -0,0 +1,180 @@
+//! Step-through viewer for recorded games.
+//!
+//! Usage: `canasta_rl replay <records file> [--game N] [--model PATH]`
+//!
+//! Loads game `N` (counting from 1) of a file of game records, as written by `model_eval`, and
+//! steps through it turn by turn. When a model is given, the values it assigns to every legal
+//! action of the seat to play are shown as well.
+
+use crate::canastautil::notation::GameRecord;
+use crate::canastautil::{Game, GameState, Play};
+use crate::model_eval::TrainedAgent;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "Commands: [n]ext turn, [b]ack a turn, [s]tep one play, [u]ndo one play, \
+                    [g]o to play N, [q]uit";
+
+pub fn run(args: &[String]) {
+    if let Err(e) = view(args) {
+        println!("{}", e);
+        println!("Usage: canasta_rl replay <records file> [--game N] [--model PATH]");
+    }
+}
+
+fn view(args: &[String]) -> Result<(), String> {
+    let mut path: Option<&str> = None;
+    let mut game_number: usize = 1;
+    let mut model_path: Option<&str> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--game" => {
+                let value = args.next().ok_or("Missing value for --game")?;
+                game_number = value
+                    .parse()
+                    .map_err(|_| format!("Invalid game number: {}", value))?;
+            }
+            "--model" => model_path = Some(args.next().ok_or("Missing value for --model")?),
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.ok_or("Missing records file")?;
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let records = GameRecord::parse_all(&contents)?;
+    let record = records
+        .get(game_number.wrapping_sub(1))
+        .ok_or(format!("{} contains {} games", path, records.len()))?;
+    let model = match model_path {
+        Some(model_path) => Some(TrainedAgent::load(model_path)?),
+        None => None,
+    };
+
+    // Every position of the game, so stepping back does not need to replay from the start
+    let mut positions: Vec<Game> = vec![record.replay_to(0)?];
+    for play in record.plays.iter() {
+        let mut next = positions[positions.len() - 1].detached_clone();
+        if next.finished || !next.check_legal(*play) {
+            return Err(format!("Play {} ({}) is illegal", positions.len(), play));
+        }
+        next.execute_play(*play);
+        positions.push(next);
+    }
+
+    println!("{}", HELP);
+    let mut position: usize = 0;
+    let stdin = io::stdin();
+    loop {
+        show(record, &positions[position], position, model.as_ref());
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            return Ok(());
+        }
+        let mut words = line.split_whitespace();
+        match words.next().unwrap_or("n") {
+            "n" => position = next_turn(record, position),
+            "b" => position = previous_turn(record, position),
+            "s" => position = (position + 1).min(record.plays.len()),
+            "u" => position = position.saturating_sub(1),
+            "g" => match words.next().and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) => position = n.min(record.plays.len()),
+                None => println!("Usage: g N"),
+            },
+            "q" => return Ok(()),
+            _ => println!("{}", HELP),
+        }
+    }
+}
+
+// Turns end with a discard or with going out
+fn ends_turn(play: &Play) -> bool {
+    matches!(play, Play::Discard(_) | Play::GoOut)
+}
+
+fn next_turn(record: &GameRecord, position: usize) -> usize {
+    match record.plays[position..].iter().position(ends_turn) {
+        Some(i) => position + i + 1,
+        None => record.plays.len(),
+    }
+}
+
+fn previous_turn(record: &GameRecord, position: usize) -> usize {
+    if position == 0 {
+        return 0;
+    }
+    match record.plays[..position - 1].iter().rposition(ends_turn) {
+        Some(i) => i + 1,
+        None => 0,
+    }
+}
+
+fn show(record: &GameRecord, game: &Game, position: usize, model: Option<&TrainedAgent>) {
+    let seat = game.turn.get() as usize;
+    println!();
+    println!("Play {}/{}", position, record.plays.len());
+    print!("{}", game);
+    match record.plays.get(position) {
+        Some(play) => println!(
+            "Next: {} ({}) by Seat {} ({})",
+            play,
+            play.name(),
+            seat,
+            record.seats[seat]
+        ),
+        None => println!("Result: {:?}", game.get_scores()),
+    }
+    if let (Some(model), false) = (model, game.finished) {
+        let values = model.q_values(&GameState { game: game.clone() });
+        let mut legal: Vec<(Play, f32)> = Play::iterator()
+            .zip(game.legal_mask().iter())
+            .filter(|(_, legal)| **legal)
+            .map(|(play, _)| (*play, values[play.index()]))
+            .collect();
+        legal.sort_by(|a, b| b.1.total_cmp(&a.1));
+        println!("Q-values:");
+        for (play, value) in legal {
+            let marker = if record.plays.get(position) == Some(&play) { "<- played" } else { "" };
+            println!("  {:>6} {:>10.3} {}", play.to_string(), value, marker);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canastautil::{Card, PlayableCardSubset};
+
+    // Two turns: draw, meld and discard, then draw and go out
+    fn record() -> GameRecord {
+        let mut record = GameRecord::new(&Game::new(2, 2, 2, 11), vec![String::new(); 4]);
+        record.plays = vec![
+            Play::Draw,
+            Play::Play(PlayableCardSubset::Four),
+            Play::Discard(Card::Three),
+            Play::Draw,
+            Play::GoOut,
+        ];
+        record
+    }
+
+    #[test]
+    fn next_turn_stops_after_the_turn_ending_play() {
+        let record = record();
+        assert_eq!(next_turn(&record, 0), 3);
+        assert_eq!(next_turn(&record, 2), 3);
+        assert_eq!(next_turn(&record, 3), 5);
+        assert_eq!(next_turn(&record, 5), 5);
+    }
+
+    #[test]
+    fn previous_turn_goes_back_to_the_start_of_the_turn() {
+        let record = record();
+        assert_eq!(previous_turn(&record, 0), 0);
+        assert_eq!(previous_turn(&record, 2), 0);
+        assert_eq!(previous_turn(&record, 3), 0);
+        assert_eq!(previous_turn(&record, 4), 3);
+        assert_eq!(previous_turn(&record, 5), 3);
+    }
+}
@@ -0,0 +1,160 @@
+//! Self-play training: a single driver steps `N` games in lockstep and routes every decision to
+//! the learner of the seat to act, so no seat waits on another and the games play out the same
+//! way every time they are given the same seed and learners.
+//!
+//! The decisions of a seat in all the games are evaluated in one batched forward pass.
+
+use crate::dqn::{DQNAgentTrainer, QNetworkDevice, QNetworkModel};
+use canasta_rl::canastautil::env::CanastaEnv;
+use canasta_rl::canastautil::{
+    Action, Game, GameState, ACTION_SIZE, INNER_SIZE, PLAYERS_PER_TEAM, STATE_SIZE, TEAMS_COUNT,
+};
+use canasta_rl::mdp::VecEnv;
+use dfdx::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+pub const PLAYERS: usize = (PLAYERS_PER_TEAM * TEAMS_COUNT) as usize;
+
+/// The learner of one seat, training the network `M`.
+pub type Trainer<M = QNetworkDevice<STATE_SIZE, ACTION_SIZE, INNER_SIZE>> = DQNAgentTrainer<
+    GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>,
+    STATE_SIZE,
+    ACTION_SIZE,
+    INNER_SIZE,
+    M,
+>;
+
+type Decision = (GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>, Action);
+
+/// `N` games played by the same trainers, one for every seat. A game that ends is dealt again at
+/// once, so the games are all at different points of their episodes.
+///
+/// A seat learns from each of its decisions once it is to act again in the same game, its next
+/// state being what it sees then. When a game ends, every seat learns from its last decision in
+/// it with its final reward from `Game::rewards`.
+pub struct SelfPlay<const N: usize> {
+    envs: VecEnv<CanastaEnv<PLAYERS_PER_TEAM, TEAMS_COUNT>, N>,
+    // The last decision of every seat in every game, waiting for what the seat sees next
+    pending: [[Option<Decision>; PLAYERS]; N],
+    // Exploration of all the seats
+    rng: StdRng,
+    seed: u64,
+    // Games dealt so far
+    dealt: u32,
+}
+
+impl<const N: usize> SelfPlay<N> {
+    /// Deals `N` games. Every deal and exploration decision follows from `seed`.
+    pub fn new(seed: u64) -> SelfPlay<N> {
+        let seeds = std::array::from_fn(|i| Some(episode_seed(seed, i as u32)));
+        SelfPlay {
+            envs: VecEnv::new(std::array::from_fn(|_| CanastaEnv::new()), seeds),
+            pending: std::array::from_fn(|_| Default::default()),
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            dealt: N as u32,
+        }
+    }
+
+    /// Plays until at least `episodes` games end, returning all the games that ended in the
+    /// order they did. Several games may end on the last step.
+    pub fn play<M>(&mut self, trainers: &mut [Trainer<M>; PLAYERS], episodes: usize) -> Vec<Game>
+    where
+        M: QNetworkModel<STATE_SIZE, ACTION_SIZE>
+            + Module<
+                Tensor<Rank2<N, STATE_SIZE>, f32, Cuda>,
+                Output = Tensor<Rank2<N, ACTION_SIZE>, f32, Cuda>,
+            >,
+    {
+        let mut ended = Vec::new();
+        while ended.len() < episodes {
+            let seats = self.envs.current_players();
+            let observations = self.envs.observations();
+            for (i, seat) in seats.iter().enumerate() {
+                if let Some((state, action)) = self.pending[i][*seat].take() {
+                    let next_state = observations[i].clone();
+                    trainers[*seat].observe(state, action, 0.0, next_state, false);
+                }
+            }
+            let mut actions: [Option<Action>; N] = std::array::from_fn(|_| None);
+            for (seat, trainer) in trainers.iter().enumerate() {
+                if !seats.contains(&seat) {
+                    continue;
+                }
+                let acting = seats.map(|s| s == seat);
+                let chosen = trainer.act_batch(observations, &acting, &mut self.rng);
+                for (i, action) in chosen.into_iter().enumerate() {
+                    if action.is_some() {
+                        actions[i] = action;
+                    }
+                }
+            }
+            let actions = actions.map(|action| action.unwrap());
+            for i in 0..N {
+                self.pending[i][seats[i]] = Some(decision(&observations[i], &actions[i]));
+            }
+            let results = self.envs.step(&actions);
+            for (i, (_, done, info)) in results.into_iter().enumerate() {
+                if !done {
+                    continue;
+                }
+                let end = &self.envs.observations()[i];
+                for (seat, decision) in self.pending[i].iter_mut().enumerate() {
+                    if let Some((state, action)) = decision.take() {
+                        let reward = info.rewards[seat];
+                        trainers[seat].observe(state, action, reward, end.clone(), true);
+                    }
+                }
+                ended.push(end.game.clone());
+                let seed = episode_seed(self.seed, self.dealt);
+                self.envs.reset(i, Some(seed));
+                self.dealt += 1;
+            }
+        }
+        ended
+    }
+}
+
+// The decision to keep until the seat sees what follows. The state is detached from the game,
+// whose boards the seats of a team share, so that later melds do not change it.
+fn decision(state: &GameState<PLAYERS_PER_TEAM, TEAMS_COUNT>, action: &Action) -> Decision {
+    let state = GameState {
+        game: state.game.detached_clone(),
+    };
+    (state, action.clone())
+}
+
+// The seed of the n-th game dealt, so that every run plays its own sequence of deals
+fn episode_seed(seed: u64, n: u32) -> u64 {
+    (seed << 32) | u64::from(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canasta_rl::mdp::Environment;
+
+    #[test]
+    fn decisions_keep_the_state_they_were_made_in() {
+        let mut selfplay: SelfPlay<1> = SelfPlay::new(3);
+        let state = selfplay.envs.observations()[0].clone();
+        let seat = selfplay.envs.current_players()[0];
+        let action = selfplay.envs.envs()[0].legal_actions().pop().unwrap();
+        selfplay.pending[0][seat] = Some(decision(&state, &action));
+        let encoded: [f32; STATE_SIZE] = state.into();
+
+        // Play on until a meld lands on a board
+        let melds = |selfplay: &SelfPlay<1>| -> usize {
+            let table = selfplay.envs.envs()[0].game().table();
+            table.teams.iter().map(|team| team.melds.len()).sum()
+        };
+        while melds(&selfplay) == 0 {
+            let action = selfplay.envs.envs()[0].legal_actions().pop().unwrap();
+            selfplay.envs.step(&[action]);
+        }
+        let (kept, _) = selfplay.pending[0][seat].clone().unwrap();
+        let kept: [f32; STATE_SIZE] = kept.into();
+        assert_eq!(kept, encoded);
+    }
+}
@@ -0,0 +1,532 @@
+//! Local game server for remote agents and user interfaces.
+//!
+//! Usage: `canasta_rl serve [--addr HOST:PORT]` (127.0.0.1:8080 by default)
+//!
+//! Requests and responses are JSON, with cards and plays written in the notation of
+//! `canastautil::notation`:
+//!
+//! - `POST /games` with an optional `{"seed": 42}` creates a game and returns its observation
+//! - `GET /games/{id}?seat=N` returns what seat `N` sees, or what a spectator sees without `seat`
+//! - `POST /games/{id}/plays` with `{"seat": 0, "play": "M:K"}` makes a play for the seat to play
+//! - `GET /games/{id}/record` returns the game record once the game is over, since it holds the seed
+//! - `GET /games/{id}/events` opens a WebSocket that sends the spectator observation, then an event
+//!   for every play
+
+use crate::canastautil::notation::GameRecord;
+use crate::canastautil::{self, Game, Play};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+
+pub fn run(args: &[String]) {
+    let addr = match args {
+        [] => DEFAULT_ADDR,
+        [flag, addr] if flag == "--addr" => addr,
+        _ => {
+            println!("Usage: canasta_rl serve [--addr HOST:PORT]");
+            return;
+        }
+    };
+    match GameServer::bind(addr) {
+        Ok(server) => {
+            println!("Serving games on http://{}", server.addr());
+            server.serve();
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// What one seat, or a spectator, can see of a game.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub id: usize,
+    /// The seat observing, None for a spectator.
+    pub seat: Option<u8>,
+    /// The seat to play.
+    pub turn: u8,
+    pub total_turns: u16,
+    pub has_drawn: bool,
+    pub finished: bool,
+    pub stock: u8,
+    /// The discard pile, top card last.
+    pub discard_pile: Vec<String>,
+    pub frozen: bool,
+    /// The observing seat's hand.
+    pub hand: Option<Vec<String>>,
+    pub seats: Vec<SeatObservation>,
+    pub teams: Vec<TeamObservation>,
+    /// The legal plays of the observing seat, empty unless it is to play.
+    pub legal_plays: Vec<String>,
+    /// Final scores by seat, once the game is over.
+    pub scores: Option<Vec<i16>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SeatObservation {
+    pub seat: u8,
+    pub team: u8,
+    pub cards: usize,
+    /// Cards known to be in the hand.
+    pub known: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TeamObservation {
+    pub team: u8,
+    pub score: u16,
+    pub melds: Vec<MeldObservation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MeldObservation {
+    pub card: String,
+    pub naturals: u8,
+    pub jokers: u8,
+    pub twos: u8,
+    pub canasta: bool,
+}
+
+/// A message sent to the WebSocket subscribers of a game.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Sent once on connection.
+    State { state: Observation },
+    /// A play was made, `state` is the spectator observation after it.
+    Play {
+        seat: u8,
+        play: String,
+        state: Observation,
+    },
+}
+
+#[derive(Deserialize)]
+struct NewGame {
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Move {
+    seat: u8,
+    play: String,
+}
+
+struct ServerGame {
+    game: Game,
+    record: GameRecord,
+    subscribers: Vec<Sender<String>>,
+}
+
+type Reply = Result<String, (u16, String)>;
+
+/// An HTTP server holding any number of games, identified by their index.
+pub struct GameServer {
+    server: Server,
+    games: Arc<Mutex<Vec<ServerGame>>>,
+}
+
+impl GameServer {
+    pub fn bind(addr: &str) -> Result<GameServer, String> {
+        let server = Server::http(addr).map_err(|e| format!("Could not bind {}: {}", addr, e))?;
+        Ok(GameServer {
+            server,
+            games: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    pub fn addr(&self) -> String {
+        self.server.server_addr().to_string()
+    }
+
+    /// Handles requests until the process ends.
+    pub fn serve(&self) {
+        for request in self.server.incoming_requests() {
+            self.handle(request);
+        }
+    }
+
+    fn handle(&self, mut request: Request) {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let method = request.method().clone();
+        let reply = match (&method, segments.as_slice()) {
+            (Method::Post, ["games"]) => self.create(&mut request),
+            (Method::Get, ["games", id]) => self.observe(id, query),
+            (Method::Post, ["games", id, "plays"]) => self.play(id, &mut request),
+            (Method::Get, ["games", id, "record"]) => self.record(id),
+            (Method::Get, ["games", id, "events"]) => {
+                if let Err((status, message)) = self.subscribe(id, request) {
+                    println!("Event subscription failed ({}): {}", status, message);
+                }
+                return;
+            }
+            _ => Err((404, format!("No route for {} {}", method, path))),
+        };
+        let (status, body) = match reply {
+            Ok(body) => (200, body),
+            Err((status, message)) => (status, serde_json::json!({ "error": message }).to_string()),
+        };
+        let response = Response::from_string(body)
+            .with_status_code(StatusCode(status))
+            .with_header(header("Content-Type", "application/json"));
+        // The client may have gone away, there is nobody left to tell
+        let _ = request.respond(response);
+    }
+
+    fn create(&self, request: &mut Request) -> Reply {
+        let new_game: NewGame = read_json(request)?;
+        let game = Game::new_seeded(
+            canastautil::TEAMS_COUNT,
+            canastautil::PLAYERS_PER_TEAM,
+            canastautil::DECKS,
+            canastautil::HAND_SIZE,
+            new_game.seed.unwrap_or_else(rand::random),
+        );
+        let seats = (0..canastautil::TEAMS_COUNT * canastautil::PLAYERS_PER_TEAM)
+            .map(|_| "Remote".to_string())
+            .collect();
+        let record = GameRecord::new(&game, seats);
+        let mut games = self.games.lock().unwrap();
+        games.push(ServerGame {
+            game,
+            record,
+            subscribers: Vec::new(),
+        });
+        let id = games.len() - 1;
+        Ok(to_json(&observe(id, &games[id].game, None)))
+    }
+
+    fn observe(&self, id: &str, query: &str) -> Reply {
+        let seat = match query.split('&').find_map(|pair| pair.strip_prefix("seat=")) {
+            Some(seat) => Some(parse_seat(seat)?),
+            None => None,
+        };
+        let games = self.games.lock().unwrap();
+        let (id, game) = find(&games, id)?;
+        Ok(to_json(&observe(id, &game.game, seat)))
+    }
+
+    fn play(&self, id: &str, request: &mut Request) -> Reply {
+        let mv: Move = read_json(request)?;
+        let play: Play = mv.play.parse().map_err(|e| (400, e))?;
+        let mut games = self.games.lock().unwrap();
+        let (id, _) = find(&games, id)?;
+        let server_game = &mut games[id];
+        let game = &mut server_game.game;
+        if game.finished {
+            return Err((409, "The game is over".to_string()));
+        }
+        if mv.seat != game.turn.get() {
+            return Err((409, format!("Seat {} is to play", game.turn.get())));
+        }
+        if !game.check_legal(play) {
+            return Err((400, format!("{} is not legal", play)));
+        }
+        game.execute_play(play);
+        server_game.record.push(play);
+        if game.finished {
+            server_game.record.finish(game);
+        }
+
+        let event = to_json(&Event::Play {
+            seat: mv.seat,
+            play: play.to_string(),
+            state: observe(id, game, None),
+        });
+        server_game
+            .subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        Ok(to_json(&observe(id, &server_game.game, Some(mv.seat))))
+    }
+
+    fn record(&self, id: &str) -> Reply {
+        let games = self.games.lock().unwrap();
+        let (_, game) = find(&games, id)?;
+        // The seed gives away every hand and the stock, so it stays hidden until the end
+        if !game.game.finished {
+            return Err((409, "The game is not over".to_string()));
+        }
+        Ok(serde_json::json!({ "record": game.record.to_string() }).to_string())
+    }
+
+    // Upgrades the request to a WebSocket fed by a thread of its own
+    fn subscribe(&self, id: &str, request: Request) -> Result<(), (u16, String)> {
+        let key = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Sec-WebSocket-Key"))
+            .map(|h| h.value.as_str().to_string());
+        let key = match key {
+            Some(key) => key,
+            None => {
+                let response = Response::from_string("Expected a WebSocket handshake")
+                    .with_status_code(StatusCode(400));
+                let _ = request.respond(response);
+                return Err((400, "Missing Sec-WebSocket-Key".to_string()));
+            }
+        };
+        let (sender, receiver) = mpsc::channel::<String>();
+        {
+            let mut games = self.games.lock().unwrap();
+            let (id, _) = match find(&games, id) {
+                Ok(found) => found,
+                Err((status, message)) => {
+                    let response =
+                        Response::from_string(message.clone()).with_status_code(StatusCode(status));
+                    let _ = request.respond(response);
+                    return Err((status, message));
+                }
+            };
+            let state = observe(id, &games[id].game, None);
+            sender.send(to_json(&Event::State { state })).unwrap();
+            games[id].subscribers.push(sender);
+        }
+
+        let response = Response::empty(StatusCode(101))
+            .with_header(header("Connection", "Upgrade"))
+            .with_header(header(
+                "Sec-WebSocket-Accept",
+                &tungstenite::handshake::derive_accept_key(key.as_bytes()),
+            ));
+        let stream = request.upgrade("websocket", response);
+        thread::spawn(move || {
+            let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+            for event in receiver {
+                if socket.send(Message::Text(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// What `seat` sees of `game`, or what a spectator sees if `seat` is None.
+pub fn observe(id: usize, game: &Game, seat: Option<u8>) -> Observation {
+    let table = game.table();
+    let notation = |cards: &[canastautil::Card]| -> Vec<String> {
+        cards
+            .iter()
+            .map(|card| card.notation().to_string())
+            .collect()
+    };
+    let legal_plays = if seat == Some(table.turn) && !table.finished {
+        Play::iterator()
+            .zip(game.legal_mask().iter())
+            .filter(|(_, legal)| **legal)
+            .map(|(play, _)| play.to_string())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Observation {
+        id,
+        seat,
+        turn: table.turn,
+        total_turns: table.total_turns,
+        has_drawn: table.has_drawn,
+        finished: table.finished,
+        stock: table.stock,
+        discard_pile: notation(&table.discard_pile),
+        frozen: table.frozen,
+        hand: seat.map(|seat| notation(&table.seats[seat as usize].hand)),
+        seats: table
+            .seats
+            .iter()
+            .map(|s| SeatObservation {
+                seat: s.seat,
+                team: s.team,
+                cards: s.hand.len(),
+                known: notation(&s.known),
+            })
+            .collect(),
+        teams: table
+            .teams
+            .iter()
+            .map(|t| TeamObservation {
+                team: t.team,
+                score: t.score,
+                melds: t
+                    .melds
+                    .iter()
+                    .map(|meld| MeldObservation {
+                        card: meld.card.notation().to_string(),
+                        naturals: meld.naturals,
+                        jokers: meld.jokers,
+                        twos: meld.twos,
+                        canasta: meld.canasta,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        legal_plays,
+        scores: if table.finished {
+            Some(game.get_scores())
+        } else {
+            None
+        },
+    }
+}
+
+fn find<'a>(games: &'a [ServerGame], id: &str) -> Result<(usize, &'a ServerGame), (u16, String)> {
+    match id.parse::<usize>() {
+        Ok(index) if index < games.len() => Ok((index, &games[index])),
+        _ => Err((404, format!("No game {}", id))),
+    }
+}
+
+fn parse_seat(seat: &str) -> Result<u8, (u16, String)> {
+    match seat.parse::<u8>() {
+        Ok(seat) if seat < canastautil::TEAMS_COUNT * canastautil::PLAYERS_PER_TEAM => Ok(seat),
+        _ => Err((400, format!("Invalid seat: {}", seat))),
+    }
+}
+
+// Reads a JSON body, an empty body is read as an empty object
+fn read_json<T: DeserializeOwned>(request: &mut Request) -> Result<T, (u16, String)> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|e| (400, e.to_string()))?;
+    if body.trim().is_empty() {
+        body = "{}".to_string();
+    }
+    serde_json::from_str(&body).map_err(|e| (400, format!("Invalid request: {}", e)))
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn start() -> String {
+        let server = GameServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.addr();
+        thread::spawn(move || server.serve());
+        addr
+    }
+
+    // A minimal HTTP/1.1 client, returning the status and the body
+    fn request(addr: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            addr,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, body.to_string())
+    }
+
+    fn observation(addr: &str, path: &str) -> Observation {
+        let (status, body) = request(addr, "GET", path, "");
+        assert_eq!(status, 200, "{}", body);
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[test]
+    fn play_over_http() {
+        let addr = start();
+        let (status, body) = request(&addr, "POST", "/games", r#"{"seed": 7}"#);
+        assert_eq!(status, 200, "{}", body);
+        let created: Observation = serde_json::from_str(&body).unwrap();
+        assert_eq!(created.id, 0);
+        assert_eq!(created.hand, None);
+
+        let to_play = observation(&addr, &format!("/games/0?seat={}", created.turn));
+        assert_eq!(to_play.hand.as_ref().unwrap().len(), 13);
+        assert!(to_play.legal_plays.contains(&"D".to_string()));
+        let waiting = (created.turn + 1) % 4;
+        assert!(observation(&addr, &format!("/games/0?seat={}", waiting))
+            .legal_plays
+            .is_empty());
+
+        let mv = |seat: u8, play: &str| format!(r#"{{"seat": {}, "play": "{}"}}"#, seat, play);
+        let (status, _) = request(&addr, "POST", "/games/0/plays", &mv(waiting, "D"));
+        assert_eq!(status, 409);
+        let (status, body) = request(&addr, "GET", "/games/0/record", "");
+        assert_eq!(status, 409);
+        assert!(!body.contains("record"), "{}", body);
+        let (status, _) = request(&addr, "POST", "/games/0/plays", &mv(created.turn, "X:5"));
+        assert_eq!(status, 400);
+        assert_eq!(request(&addr, "GET", "/games/1", "").0, 404);
+
+        // Play the whole game with the first legal play of every seat
+        let mut state = to_play;
+        while !state.finished {
+            let play = state.legal_plays[0].clone();
+            let (status, body) = request(&addr, "POST", "/games/0/plays", &mv(state.turn, &play));
+            assert_eq!(status, 200, "{}", body);
+            let played: Observation = serde_json::from_str(&body).unwrap();
+            state = observation(&addr, &format!("/games/0?seat={}", played.turn));
+        }
+
+        let (status, body) = request(&addr, "GET", "/games/0/record", "");
+        assert_eq!(status, 200);
+        let text: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let record: GameRecord = text["record"].as_str().unwrap().parse().unwrap();
+        assert_eq!(record.seed, 7);
+        assert_eq!(record.result, state.scores);
+        assert_eq!(Some(record.replay().unwrap().get_scores()), state.scores);
+    }
+
+    #[test]
+    fn events_over_websocket() {
+        let addr = start();
+        let created: Observation =
+            serde_json::from_str(&request(&addr, "POST", "/games", "").1).unwrap();
+        let (mut socket, _) =
+            tungstenite::connect(format!("ws://{}/games/0/events", addr)).unwrap();
+
+        let event = |socket: &mut WebSocket<_>| -> Event {
+            match socket.read().unwrap() {
+                Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                message => panic!("Unexpected message: {:?}", message),
+            }
+        };
+        assert_eq!(
+            event(&mut socket),
+            Event::State {
+                state: created.clone()
+            }
+        );
+
+        let body = format!(r#"{{"seat": {}, "play": "D"}}"#, created.turn);
+        assert_eq!(request(&addr, "POST", "/games/0/plays", &body).0, 200);
+        match event(&mut socket) {
+            Event::Play { seat, play, state } => {
+                assert_eq!(seat, created.turn);
+                assert_eq!(play, "D");
+                assert!(state.has_drawn);
+                assert_eq!(state.stock, created.stock - 1);
+            }
+            other => panic!("Unexpected event: {:?}", other),
+        }
+    }
+}
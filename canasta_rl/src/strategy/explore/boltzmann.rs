@@ -0,0 +1,121 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Module for the Boltzmann (softmax) exploration strategy.
+
+use std::cell::Cell;
+use std::str::FromStr;
+
+use crate::mdp::State;
+use crate::strategy::explore::{best_legal, DecayPer, EpsilonDecay, ExplorationStrategy};
+use rand::{Rng, RngCore};
+
+/// The Boltzmann exploration strategy. This strategy takes every legal action with a probability
+/// proportional to `exp(value / temperature)`, so that a high temperature explores almost
+/// uniformly and a temperature near zero is nearly greedy.
+#[derive(Clone, Debug)]
+pub struct Boltzmann {
+    start: f64,
+    decay: EpsilonDecay,
+    per: DecayPer,
+    // Steps or episodes so far
+    elapsed: Cell<usize>,
+}
+
+impl Boltzmann {
+    /// Constructs the Boltzmann strategy with a constant `temperature`.
+    pub fn new(temperature: f64) -> Boltzmann {
+        Boltzmann::decaying(temperature, EpsilonDecay::Constant, DecayPer::Step)
+    }
+
+    /// Constructs the Boltzmann strategy with the temperature starting at `start` and following
+    /// `decay` every step or episode.
+    pub fn decaying(start: f64, decay: EpsilonDecay, per: DecayPer) -> Boltzmann {
+        Boltzmann {
+            start,
+            decay,
+            per,
+            elapsed: Cell::new(0),
+        }
+    }
+
+    /// The temperature the next action is picked with.
+    pub fn temperature(&self) -> f64 {
+        self.decay.value(self.start, self.elapsed.get())
+    }
+}
+
+impl FromStr for Boltzmann {
+    type Err = String;
+
+    /// Parses `TEMPERATURE` for a constant temperature, `linear:START:END:OVER` or
+    /// `exponential:START:FACTOR:MIN`. The temperature decays every step, or every episode with
+    /// `:episode` appended.
+    fn from_str(s: &str) -> Result<Boltzmann, String> {
+        let (start, decay, per) = EpsilonDecay::parse(s)?;
+        Ok(Boltzmann::decaying(start, decay, per))
+    }
+}
+
+impl<S: State> ExplorationStrategy<S> for Boltzmann {
+    fn pick_action(&self, _: &S, values: &[f64], legal: &[bool], rng: &mut dyn RngCore) -> usize {
+        let temperature = self.temperature();
+        if self.per == DecayPer::Step {
+            self.elapsed.set(self.elapsed.get() + 1);
+        }
+        let best = best_legal(values, legal);
+        if temperature <= 0.0 {
+            return best;
+        }
+        // Relative to the best value, so that the weights can't overflow
+        let weights: Vec<f64> = (values.iter().zip(legal))
+            .map(|(value, legal)| match legal {
+                true => ((value - values[best]) / temperature).exp(),
+                false => 0.0,
+            })
+            .collect();
+        let mut pick = rng.gen::<f64>() * weights.iter().sum::<f64>();
+        for (i, weight) in weights.iter().enumerate() {
+            if legal[i] && pick < *weight {
+                return i;
+            }
+            pick -= weight;
+        }
+        best
+    }
+
+    fn end_episode(&self) {
+        if self.per == DecayPer::Episode {
+            self.elapsed.set(self.elapsed.get() + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::explore::tests::Only;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn temperature_sets_how_greedy() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let values = [1.0, 0.0, 5.0, 0.5];
+        let legal = [true, true, false, true];
+        let mut picks = [0; 4];
+        let hot = Boltzmann::new(1000.0);
+        for _ in 0..1000 {
+            picks[hot.pick_action(&Only, &values, &legal, &mut rng)] += 1;
+        }
+        assert_eq!(picks[2], 0);
+        assert!(picks.iter().enumerate().all(|(i, n)| i == 2 || *n > 250));
+
+        let cold: Boltzmann = "exponential:1:0.001:0.001:episode".parse().unwrap();
+        ExplorationStrategy::<Only>::end_episode(&cold);
+        for _ in 0..100 {
+            assert_eq!(cold.pick_action(&Only, &values, &legal, &mut rng), 0);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Module for the epsilon-greedy exploration strategy.
+
+use std::cell::Cell;
+use std::str::FromStr;
+
+use crate::mdp::State;
+use crate::strategy::explore::{best_legal, random_legal, ExplorationStrategy};
+use rand::{Rng, RngCore};
+
+/// How epsilon, or the temperature of `Boltzmann`, changes as steps or episodes go by.
+#[derive(Clone, Debug)]
+pub enum EpsilonDecay {
+    Constant,
+    /// In a straight line down to `end` over `over` steps or episodes, then kept at `end`.
+    Linear {
+        end: f64,
+        over: usize,
+    },
+    /// Multiplied by `factor` after every step or episode, down to `min`.
+    Exponential {
+        factor: f64,
+        min: f64,
+    },
+}
+
+impl EpsilonDecay {
+    /// The value after `elapsed` steps or episodes, starting from `start`.
+    pub fn value(&self, start: f64, elapsed: usize) -> f64 {
+        match *self {
+            EpsilonDecay::Constant => start,
+            EpsilonDecay::Linear { end, over } => {
+                let progress = elapsed.min(over) as f64 / over.max(1) as f64;
+                start + (end - start) * progress
+            }
+            EpsilonDecay::Exponential { factor, min } => {
+                (start * factor.powf(elapsed as f64)).max(min)
+            }
+        }
+    }
+
+    // Parses a starting value and its decay: `VALUE` for a constant one,
+    // `linear:START:END:OVER` or `exponential:START:FACTOR:MIN`, decaying every step, or every
+    // episode with `:episode` appended
+    pub(super) fn parse(s: &str) -> Result<(f64, EpsilonDecay, DecayPer), String> {
+        let mut parts: Vec<&str> = s.split(':').collect();
+        let per = match parts.last() {
+            Some(&"episode") => DecayPer::Episode,
+            _ => DecayPer::Step,
+        };
+        if matches!(parts.last(), Some(&"episode") | Some(&"step")) {
+            parts.pop();
+        }
+        let number = |i: usize| -> Result<f64, String> {
+            parts[i]
+                .parse()
+                .map_err(|_| format!("Invalid number: {}", parts[i]))
+        };
+        let decay = match parts[..] {
+            [_] => EpsilonDecay::Constant,
+            ["linear", _, _, over] => EpsilonDecay::Linear {
+                end: number(2)?,
+                over: over
+                    .parse()
+                    .map_err(|_| format!("Invalid number: {}", over))?,
+            },
+            ["exponential", _, _, _] => EpsilonDecay::Exponential {
+                factor: number(2)?,
+                min: number(3)?,
+            },
+            _ => return Err(format!("Unknown schedule: {}", s)),
+        };
+        let start = match decay {
+            EpsilonDecay::Constant => number(0)?,
+            _ => number(1)?,
+        };
+        Ok((start, decay, per))
+    }
+}
+
+/// What epsilon, or the temperature of `Boltzmann`, decays with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecayPer {
+    /// Every action picked.
+    Step,
+    /// Every episode ended, as told by `ExplorationStrategy::end_episode`.
+    Episode,
+}
+
+/// The epsilon-greedy exploration strategy. This strategy takes a random legal action with
+/// probability epsilon, and the legal action of highest value otherwise.
+#[derive(Clone, Debug)]
+pub struct EpsilonGreedy {
+    start: f64,
+    decay: EpsilonDecay,
+    per: DecayPer,
+    // Steps or episodes so far
+    elapsed: Cell<usize>,
+}
+
+impl EpsilonGreedy {
+    /// Constructs the epsilon-greedy strategy with a constant `epsilon`.
+    pub fn new(epsilon: f64) -> EpsilonGreedy {
+        EpsilonGreedy::decaying(epsilon, EpsilonDecay::Constant, DecayPer::Step)
+    }
+
+    /// Constructs the epsilon-greedy strategy with epsilon starting at `start` and following
+    /// `decay` every step or episode.
+    pub fn decaying(start: f64, decay: EpsilonDecay, per: DecayPer) -> EpsilonGreedy {
+        EpsilonGreedy {
+            start,
+            decay,
+            per,
+            elapsed: Cell::new(0),
+        }
+    }
+
+    /// The probability of the next action being random.
+    pub fn epsilon(&self) -> f64 {
+        self.decay.value(self.start, self.elapsed.get())
+    }
+}
+
+impl FromStr for EpsilonGreedy {
+    type Err = String;
+
+    /// Parses `EPSILON` for a constant epsilon, `linear:START:END:OVER` or
+    /// `exponential:START:FACTOR:MIN`. Epsilon decays every step, or every episode with
+    /// `:episode` appended.
+    fn from_str(s: &str) -> Result<EpsilonGreedy, String> {
+        let (start, decay, per) = EpsilonDecay::parse(s)?;
+        Ok(EpsilonGreedy::decaying(start, decay, per))
+    }
+}
+
+impl<S: State> ExplorationStrategy<S> for EpsilonGreedy {
+    fn pick_action(&self, _: &S, values: &[f64], legal: &[bool], rng: &mut dyn RngCore) -> usize {
+        let epsilon = self.epsilon();
+        if self.per == DecayPer::Step {
+            self.elapsed.set(self.elapsed.get() + 1);
+        }
+        if rng.gen::<f64>() < epsilon {
+            random_legal(legal, rng)
+        } else {
+            best_legal(values, legal)
+        }
+    }
+
+    fn end_episode(&self) {
+        if self.per == DecayPer::Episode {
+            self.elapsed.set(self.elapsed.get() + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::explore::tests::Only;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn epsilon_decays() {
+        let linear: EpsilonGreedy = "linear:1.0:0.2:4".parse().unwrap();
+        let exponential: EpsilonGreedy = "exponential:1.0:0.5:0.2:episode".parse().unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..2 {
+            ExplorationStrategy::<Only>::pick_action(&linear, &Only, &[0.0], &[true], &mut rng);
+            ExplorationStrategy::<Only>::pick_action(
+                &exponential,
+                &Only,
+                &[0.0],
+                &[true],
+                &mut rng,
+            );
+        }
+        assert!((linear.epsilon() - 0.6).abs() < 1e-9);
+        // Only episodes count
+        assert_eq!(exponential.epsilon(), 1.0);
+        ExplorationStrategy::<Only>::end_episode(&exponential);
+        assert_eq!(exponential.epsilon(), 0.5);
+        for _ in 0..3 {
+            ExplorationStrategy::<Only>::end_episode(&exponential);
+        }
+        assert_eq!(exponential.epsilon(), 0.2);
+    }
+
+    #[test]
+    fn greedy_picks_the_best_legal_action() {
+        let greedy = EpsilonGreedy::new(0.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let values = [3.0, 1.0, 2.0];
+        let legal = [false, true, true];
+        assert_eq!(greedy.pick_action(&Only, &values, &legal, &mut rng), 2);
+    }
+}
@@ -4,14 +4,84 @@
 
 //! Module containing exploration strategies.
 
+pub use self::boltzmann::Boltzmann;
+pub use self::epsilon_greedy::{DecayPer, EpsilonDecay, EpsilonGreedy};
 pub use self::random::RandomExploration;
-use crate::mdp::{Agent, State};
+pub use self::ucb::Ucb;
+use crate::mdp::State;
+use rand::{Rng, RngCore};
 
+pub mod boltzmann;
+pub mod epsilon_greedy;
 pub mod random;
+pub mod ucb;
 
-/// Trait for exploration strategies. An exploration strategy decides, based on an `Agent`, which
-/// action to take next.
+/// Trait for exploration strategies. An exploration strategy decides which action to take next
+/// from what the learner knows of the state.
 pub trait ExplorationStrategy<S: State> {
-    /// Selects the next action to take for this `Agent`.
-    fn pick_action(&self, _: &mut dyn Agent<S>) -> S::A;
+    /// Selects the action to take in `state`, by index, given the value the learner assigns to
+    /// every action and which of them are legal. `values` and `legal` are indexed like the actions
+    /// of the learner: the action indices for `DQNAgentTrainer`, `State::actions` for
+    /// `AgentTrainer`.
+    fn pick_action(
+        &self,
+        state: &S,
+        values: &[f64],
+        legal: &[bool],
+        rng: &mut dyn RngCore,
+    ) -> usize;
+
+    /// Tells the strategy that an episode ended, for strategies that change from one to the next.
+    fn end_episode(&self) {}
+}
+
+// The legal action with the highest value, the first one on ties
+fn best_legal(values: &[f64], legal: &[bool]) -> usize {
+    let mut best: Option<usize> = None;
+    for (i, value) in values.iter().enumerate() {
+        if legal[i] && best.is_none_or(|b| *value > values[b]) {
+            best = Some(i);
+        }
+    }
+    best.expect("No legal actions")
+}
+
+// A legal action picked uniformly
+fn random_legal(legal: &[bool], rng: &mut dyn RngCore) -> usize {
+    let count = legal.iter().filter(|legal| **legal).count();
+    assert!(count > 0, "No legal actions");
+    let nth = rng.gen_range(0..count);
+    (legal.iter().enumerate())
+        .filter(|(_, legal)| **legal)
+        .nth(nth)
+        .unwrap()
+        .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A state with a single action
+    #[derive(PartialEq, Eq, Hash, Clone)]
+    pub struct Only;
+
+    impl State for Only {
+        type A = ();
+        fn reward(&self) -> f64 {
+            0.0
+        }
+        fn actions(&self) -> Vec<()> {
+            vec![()]
+        }
+        fn check_legal(&self, _play: usize) -> bool {
+            true
+        }
+        fn check_legal_action(&self, _action: ()) -> bool {
+            true
+        }
+        fn legal_mask(&self) -> Vec<bool> {
+            vec![true]
+        }
+    }
 }
@@ -2,12 +2,12 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use crate::mdp::{Agent, State};
-use crate::strategy::explore::ExplorationStrategy;
+use crate::mdp::State;
+use crate::strategy::explore::{random_legal, ExplorationStrategy};
+use rand::RngCore;
 
-/// The random exploration strategy. This strategy always takes a random action, as defined for the
-/// Agent by
-/// [Agent::take_random_action()](../../../mdp/trait.Agent.html#method.take_random_action)
+/// The random exploration strategy. This strategy always takes a random legal action, whatever
+/// their values.
 pub struct RandomExploration;
 
 impl RandomExploration {
@@ -23,7 +23,7 @@ impl Default for RandomExploration {
 }
 
 impl<S: State> ExplorationStrategy<S> for RandomExploration {
-    fn pick_action(&self, agent: &mut dyn Agent<S>) -> S::A {
-        agent.pick_random_action()
+    fn pick_action(&self, _: &S, _: &[f64], legal: &[bool], rng: &mut dyn RngCore) -> usize {
+        random_legal(legal, rng)
     }
 }
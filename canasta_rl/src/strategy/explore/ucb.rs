@@ -0,0 +1,200 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Module for the count-based UCB exploration strategy.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::mdp::State;
+use crate::strategy::explore::ExplorationStrategy;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+/// The upper confidence bound exploration strategy. This strategy counts the actions taken in
+/// every state, and takes the legal action maximizing
+/// `value + c * sqrt(ln(visits of the state) / visits of the action)`, every untried action
+/// first.
+///
+/// States are counted under a key: the state itself with `new`, which suits small tabular
+/// problems where states repeat, or the SimHash of their features with `hashed`, under which
+/// similar states share their counts. Canasta states almost never repeat, so they need the
+/// latter, or every action looks untried and the first legal one is taken every time.
+pub struct Ucb<S: State, K = S> {
+    c: f64,
+    key: Box<dyn Fn(&S) -> K>,
+    // How many times every action was taken under every key
+    counts: RefCell<HashMap<K, Vec<u32>>>,
+}
+
+impl<S: State + 'static> Ucb<S> {
+    /// Constructs the UCB strategy with the exploration constant `c`, counting every state on
+    /// its own. States must not change once counted, or their counts are lost.
+    pub fn new(c: f64) -> Ucb<S> {
+        Ucb::keyed(c, S::clone)
+    }
+}
+
+impl<S: State + 'static> Ucb<S, u64> {
+    /// Constructs the UCB strategy with the exploration constant `c`, counting states by the
+    /// SimHash of their `N` features: the signs of `bits` random projections of them, drawn from
+    /// `seed`. The fewer the bits, the more states share their counts.
+    pub fn hashed<const N: usize>(c: f64, bits: usize, seed: u64) -> Ucb<S, u64>
+    where
+        S: Into<[f32; N]>,
+    {
+        assert!(bits <= 64, "A SimHash has at most 64 bits");
+        let mut rng = StdRng::seed_from_u64(seed);
+        let projections: Vec<[f32; N]> = (0..bits)
+            .map(|_| std::array::from_fn(|_| if rng.gen() { 1.0 } else { -1.0 }))
+            .collect();
+        Ucb::keyed(c, move |state: &S| {
+            let features: [f32; N] = state.clone().into();
+            let dot = |projection: &[f32; N]| -> f32 {
+                projection.iter().zip(features).map(|(p, f)| p * f).sum()
+            };
+            (projections.iter().enumerate())
+                .filter(|(_, projection)| dot(projection) > 0.0)
+                .fold(0, |hash, (bit, _)| hash | 1 << bit)
+        })
+    }
+}
+
+impl<S: State, K: Eq + Hash> Ucb<S, K> {
+    /// Constructs the UCB strategy with the exploration constant `c`, counting states under
+    /// `key`, so that the states with the same key share their counts.
+    pub fn keyed(c: f64, key: impl Fn(&S) -> K + 'static) -> Ucb<S, K> {
+        Ucb {
+            c,
+            key: Box::new(key),
+            counts: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<S: State, K: Eq + Hash> ExplorationStrategy<S> for Ucb<S, K> {
+    fn pick_action(&self, state: &S, values: &[f64], legal: &[bool], _: &mut dyn RngCore) -> usize {
+        let mut counts = self.counts.borrow_mut();
+        let counts = (counts.entry((self.key)(state))).or_insert_with(|| vec![0; values.len()]);
+        let visits: u32 = counts.iter().sum();
+        let mut best: Option<(usize, f64)> = None;
+        for (i, value) in values.iter().enumerate() {
+            if !legal[i] {
+                continue;
+            }
+            let bound = match counts[i] {
+                0 => f64::INFINITY,
+                n => value + self.c * ((visits as f64).ln() / n as f64).sqrt(),
+            };
+            if best.is_none_or(|(_, b)| bound > b) {
+                best = Some((i, bound));
+            }
+        }
+        let (action, _) = best.expect("No legal actions");
+        counts[action] += 1;
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canastautil::env::CanastaEnv;
+    use crate::canastautil::{Action, GameState, ACTION_SIZE, STATE_SIZE};
+    use crate::mdp::Environment;
+    use crate::strategy::explore::tests::Only;
+
+    // A state of two features, whose SimHash only depends on their direction
+    #[derive(PartialEq, Eq, Hash, Clone)]
+    struct Point(i32, i32);
+
+    impl State for Point {
+        type A = ();
+        fn reward(&self) -> f64 {
+            0.0
+        }
+        fn actions(&self) -> Vec<()> {
+            vec![(); 3]
+        }
+        fn check_legal(&self, _play: usize) -> bool {
+            true
+        }
+        fn check_legal_action(&self, _action: ()) -> bool {
+            true
+        }
+        fn legal_mask(&self) -> Vec<bool> {
+            vec![true; 3]
+        }
+    }
+
+    impl From<Point> for [f32; 2] {
+        fn from(point: Point) -> Self {
+            [point.0 as f32, point.1 as f32]
+        }
+    }
+
+    #[test]
+    fn untried_actions_first_then_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let ucb = Ucb::new(1.0);
+        let values = [0.0, 0.5, 0.2];
+        let legal = [true, true, true];
+        let mut picks: Vec<usize> = (0..3)
+            .map(|_| ucb.pick_action(&Only, &values, &legal, &mut rng))
+            .collect();
+        picks.sort();
+        assert_eq!(picks, vec![0, 1, 2]);
+        // All tried once, so the highest value has the highest bound
+        assert_eq!(ucb.pick_action(&Only, &values, &legal, &mut rng), 1);
+        // Then the bounds of the others catch up
+        let next: Vec<usize> = (0..4)
+            .map(|_| ucb.pick_action(&Only, &values, &legal, &mut rng))
+            .collect();
+        assert!(next.contains(&0) && next.contains(&2));
+    }
+
+    #[test]
+    fn hashed_states_share_their_counts() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let ucb = Ucb::hashed::<2>(1.0, 8, 0);
+        let (values, legal) = ([0.0; 3], [true; 3]);
+        let mut pick = |state: Point| ucb.pick_action(&state, &values, &legal, &mut rng);
+        // The same direction is the same key, so the untried actions go by in turn
+        assert_eq!(pick(Point(1, 2)), 0);
+        assert_eq!(pick(Point(2, 4)), 1);
+        assert_eq!(pick(Point(1, 2)), 2);
+        // The opposite direction flips every bit, and starts over
+        assert_eq!(pick(Point(-1, -2)), 0);
+    }
+
+    #[test]
+    fn canasta_states_repeat_under_their_hash() {
+        // Picks UCB's actions in a dealt game for `steps` steps, returning how many keys were
+        // counted and whether one of them had several actions tried
+        fn play(ucb: &Ucb<GameState<2, 2>, impl Eq + Hash>, steps: usize) -> (usize, bool) {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut env: CanastaEnv<2, 2> = CanastaEnv::new();
+            let mut state = env.reset(Some(7));
+            for _ in 0..steps {
+                let legal = state.legal_mask();
+                let action = ucb.pick_action(&state, &[0.0; ACTION_SIZE], &legal, &mut rng);
+                let (next, _, done, _) = env.step(&Action::from(action));
+                assert!(!done, "The game ended too soon");
+                state = next;
+            }
+            let counts = ucb.counts.borrow();
+            let explored = (counts.values())
+                .any(|counts| counts.iter().filter(|count| **count > 0).count() > 1);
+            (counts.len(), explored)
+        }
+
+        // Every state is new, so only the first legal action is ever tried
+        assert_eq!(play(&Ucb::new(1.0), 100), (100, false));
+        // With 4 bits, 100 steps fall in at most 16 keys, where the other actions get tried
+        let (keys, explored) = play(&Ucb::hashed::<STATE_SIZE>(1.0, 4, 0), 100);
+        assert!(keys <= 16);
+        assert!(explored);
+    }
+}
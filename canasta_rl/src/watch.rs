@@ -0,0 +1,337 @@
+//! Full-screen view of a game between agents.
+//!
+//! Usage: `canasta_rl watch [--model PATH] [--delay MS] [--record FILE]`
+//!
+//! Seats 0 and 2 are played by the model at `PATH` (by a `RandomAgent` if no model is given) and
+//! seats 1 and 3 by a `RandomAgent`, as in `model_eval::test_model`. One play is made every
+//! `--delay` milliseconds (300 by default). The record of every finished game is appended to
+//! `FILE` if given.
+
+use crate::canastautil::notation::GameRecord;
+use crate::canastautil::table::{Meld, SeatTable, Table, TeamTable};
+use crate::canastautil::{self, Game, GameState, Play};
+use crate::model_eval::{self, CanastaAgent, RandomAgent, TrainedAgent};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::time::{Duration, Instant};
+
+const HELP: &str =
+    " [space] pause  [+/-] speed  [n] step  [h] hide hands  [r] new game  [q] quit ";
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const MAX_DELAY: Duration = Duration::from_millis(5000);
+
+pub fn run(args: &[String]) {
+    if let Err(e) = watch(args) {
+        println!("{}", e);
+        println!("Usage: canasta_rl watch [--model PATH] [--delay MS] [--record FILE]");
+    }
+}
+
+fn watch(args: &[String]) -> Result<(), String> {
+    let mut model_path: Option<&str> = None;
+    let mut delay = Duration::from_millis(300);
+    let mut record_file: Option<String> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--model" => model_path = Some(args.next().ok_or("Missing value for --model")?),
+            "--delay" => {
+                let value = args.next().ok_or("Missing value for --delay")?;
+                let ms: u64 = value
+                    .parse()
+                    .map_err(|_| format!("Invalid delay: {}", value))?;
+                delay = Duration::from_millis(ms).clamp(MIN_DELAY, MAX_DELAY);
+            }
+            "--record" => {
+                record_file = Some(args.next().ok_or("Missing value for --record")?.clone())
+            }
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    let model = match model_path {
+        Some(model_path) => Some(TrainedAgent::load(model_path)?),
+        None => None,
+    };
+    let random = RandomAgent {};
+    let agents: Vec<&dyn CanastaAgent> = (0..canastautil::PLAYERS_PER_TEAM
+        * canastautil::TEAMS_COUNT)
+        .map(|i| match (&model, i % 2) {
+            (Some(model), 0) => model as &dyn CanastaAgent,
+            _ => &random as &dyn CanastaAgent,
+        })
+        .collect();
+
+    let mut watcher = Watcher::new(agents, delay, record_file);
+    let mut terminal = ratatui::init();
+    let result = watcher.run(&mut terminal);
+    ratatui::restore();
+    result.map_err(|e| e.to_string())
+}
+
+struct Watcher<'a> {
+    agents: Vec<&'a dyn CanastaAgent>,
+    game: Game,
+    record: GameRecord,
+    // Where finished games are recorded, and why the last one could not be
+    record_file: Option<String>,
+    record_error: Option<String>,
+    last_play: Option<(u8, Play)>,
+    delay: Duration,
+    paused: bool,
+    show_hands: bool,
+}
+
+impl<'a> Watcher<'a> {
+    fn new(
+        agents: Vec<&'a dyn CanastaAgent>,
+        delay: Duration,
+        record_file: Option<String>,
+    ) -> Self {
+        let (game, record) = Self::deal(&agents);
+        Watcher {
+            agents,
+            game,
+            record,
+            record_file,
+            record_error: None,
+            last_play: None,
+            delay,
+            paused: false,
+            show_hands: true,
+        }
+    }
+
+    fn deal(agents: &[&dyn CanastaAgent]) -> (Game, GameRecord) {
+        let game = Game::new(
+            canastautil::TEAMS_COUNT,
+            canastautil::PLAYERS_PER_TEAM,
+            canastautil::DECKS,
+            canastautil::HAND_SIZE,
+        );
+        let record = GameRecord::new(&game, agents.iter().map(|agent| agent.name()).collect());
+        (game, record)
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        let mut last_step = Instant::now();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let timeout = if self.paused || self.game.finished {
+                Duration::from_secs(1)
+            } else {
+                self.delay.saturating_sub(last_step.elapsed())
+            };
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char(' ') => self.paused = !self.paused,
+                        KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Up => {
+                            self.delay = (self.delay / 2).max(MIN_DELAY)
+                        }
+                        KeyCode::Char('-') | KeyCode::Down => {
+                            self.delay = (self.delay * 2).min(MAX_DELAY)
+                        }
+                        KeyCode::Char('n') | KeyCode::Right => {
+                            self.paused = true;
+                            self.step();
+                        }
+                        KeyCode::Char('h') => self.show_hands = !self.show_hands,
+                        KeyCode::Char('r') => {
+                            (self.game, self.record) = Self::deal(&self.agents);
+                            self.last_play = None;
+                            last_step = Instant::now();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if !self.paused && last_step.elapsed() >= self.delay {
+                self.step();
+                last_step = Instant::now();
+            }
+        }
+    }
+
+    // Makes the next play, saving the record once the game is over
+    fn step(&mut self) {
+        if self.game.finished {
+            return;
+        }
+        let seat = self.game.turn.get();
+        let state = GameState {
+            game: self.game.clone(),
+        };
+        let play = self.agents[seat as usize].get_action(&state);
+        self.game.execute_play(play);
+        self.record.push(play);
+        self.last_play = Some((seat, play));
+        if self.game.finished {
+            self.record.finish(&self.game);
+            if let Some(path) = &self.record_file {
+                self.record_error = model_eval::save_record(path, &self.record).err();
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let table = self.game.table();
+        let [status, area, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [top, middle, bottom] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Min(0),
+            Constraint::Length(5),
+        ])
+        .areas(area);
+        let [left, center, right] = Layout::horizontal([
+            Constraint::Percentage(20),
+            Constraint::Min(0),
+            Constraint::Percentage(20),
+        ])
+        .areas(middle);
+        let [team_0, piles, team_1] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(5),
+            Constraint::Min(0),
+        ])
+        .areas(center);
+
+        frame.render_widget(Paragraph::new(self.status_line(&table)), status);
+        // Partners sit opposite each other
+        for (seat, rect) in [(0, bottom), (1, left), (2, top), (3, right)] {
+            self.draw_seat(frame, &table, &table.seats[seat], rect);
+        }
+        draw_board(frame, &table.teams[0], team_0);
+        draw_board(frame, &table.teams[1], team_1);
+        self.draw_piles(frame, &table, piles);
+        frame.render_widget(
+            Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::REVERSED)),
+            help,
+        );
+    }
+
+    fn status_line(&self, table: &Table) -> String {
+        let mut status = format!(
+            " Turn {}  Play {}  Delay {}ms",
+            table.total_turns,
+            self.record.plays.len(),
+            self.delay.as_millis()
+        );
+        if table.finished {
+            let scores: Vec<String> =
+                self.game.get_scores().iter().map(|s| s.to_string()).collect();
+            status.push_str(&format!("  Finished, scores: {}", scores.join(" ")));
+            if let Some(e) = &self.record_error {
+                status.push_str(&format!("  {}", e));
+            }
+        } else if self.paused {
+            status.push_str("  PAUSED");
+        }
+        status
+    }
+
+    fn draw_seat(&self, frame: &mut Frame, table: &Table, seat: &SeatTable, rect: Rect) {
+        let to_play = seat.seat == table.turn && !table.finished;
+        let border = if to_play {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        let block = Block::bordered()
+            .border_style(border)
+            .title(format!(" Seat {} · Team {} ", seat.seat, seat.team));
+        let agent = format!(
+            "{}{}",
+            self.agents[seat.seat as usize].name(),
+            if to_play && table.has_drawn { " (has drawn)" } else { "" }
+        );
+        let hand = if self.show_hands {
+            let cards: Vec<&str> = seat.hand.iter().map(|card| card.notation()).collect();
+            format!("{} cards: {}", seat.hand.len(), cards.join(" "))
+        } else {
+            format!("{} cards", seat.hand.len())
+        };
+        frame.render_widget(
+            Paragraph::new(vec![Line::from(agent), Line::from(hand)])
+                .block(block)
+                .wrap(Wrap { trim: true }),
+            rect,
+        );
+    }
+
+    fn draw_piles(&self, frame: &mut Frame, table: &Table, rect: Rect) {
+        let top = match table.discard_pile.last() {
+            Some(card) => card.notation(),
+            None => "-",
+        };
+        let mut discard = Line::from(format!(
+            "Discard pile: {} cards, top {}  ",
+            table.discard_pile.len(),
+            top
+        ));
+        if table.frozen {
+            discard.push_span(ratatui::text::Span::styled(
+                "FROZEN",
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            ));
+        }
+        let last_play = match self.last_play {
+            Some((seat, play)) => format!("Last play: Seat {} {} ({})", seat, play, play.name()),
+            None => "Last play: -".to_string(),
+        };
+        let lines = vec![
+            Line::from(format!("Stock: {} cards", table.stock)),
+            discard,
+            Line::from(last_play),
+        ];
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered()), rect);
+    }
+}
+
+fn draw_board(frame: &mut Frame, team: &TeamTable, rect: Rect) {
+    let rows: Vec<Row> = team.melds.iter().map(meld_row).collect();
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Length(9),
+        Constraint::Length(7),
+        Constraint::Min(0),
+    ];
+    let meld_table = ratatui::widgets::Table::new(rows, widths)
+        .header(
+            Row::new(vec!["Card", "Naturals", "Wilds", ""])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(format!(" Team {} · {} points ", team.team, team.score)));
+    frame.render_widget(meld_table, rect);
+}
+
+// Natural canastas are red and mixed canastas black, as with the cards placed on them
+fn meld_row(meld: &Meld) -> Row<'static> {
+    let (marker, style) = match (meld.canasta, meld.is_natural()) {
+        (true, true) => (
+            "Natural canasta",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+        (true, false) => ("Mixed canasta", Style::default().add_modifier(Modifier::BOLD)),
+        (false, _) => ("", Style::default()),
+    };
+    Row::new(vec![
+        meld.card.notation().to_string(),
+        meld.naturals.to_string(),
+        format!("{}J {}T", meld.jokers, meld.twos),
+        marker.to_string(),
+    ])
+    .style(style)
+}